@@ -0,0 +1,108 @@
+use base32::Alphabet;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use xotp::totp::TOTP;
+use xotp::util::{base32_decode_verbose, MacDigest};
+
+static SECRET: &[u8] = b"12345678901234567890";
+
+// Compares generating a window of codes with a new HMAC key for each code
+// against reusing a prepared one.
+fn bench_prepared(c: &mut Criterion) {
+    let totp = TOTP::new(SECRET, MacDigest::SHA1, 6, 30);
+    let times: Vec<u64> = (0..21).map(|step| 1_234_567_890 + step * 30).collect();
+
+    let mut group = c.benchmark_group("window of 21 codes");
+    group.bench_function("get_otp", |b| {
+        b.iter(|| {
+            for time in &times {
+                black_box(totp.get_otp(black_box(*time)));
+            }
+        })
+    });
+    group.bench_function("prepared get_otp", |b| {
+        b.iter(|| {
+            let prepared = totp.prepared();
+            for time in &times {
+                black_box(prepared.get_otp(black_box(*time)));
+            }
+        })
+    });
+    group.finish();
+}
+
+// Compares generating codes at many timestamps one by one against the
+// batch method.
+fn bench_batch(c: &mut Criterion) {
+    let totp = TOTP::new(SECRET, MacDigest::SHA1, 6, 30);
+    let times: Vec<u64> = (0..10_000).map(|i| 1_234_567_890 + i * 7).collect();
+
+    let mut group = c.benchmark_group("10000 timestamps");
+    group.bench_function("get_otp", |b| {
+        b.iter(|| {
+            for time in &times {
+                black_box(totp.get_otp(black_box(*time)));
+            }
+        })
+    });
+    group.bench_function("get_otps_at", |b| {
+        b.iter(|| black_box(totp.get_otps_at(black_box(&times))))
+    });
+    group.finish();
+}
+
+// Measures the per-call cost of a single code on a long-lived generator.
+// Run with `--features mac-cache` to compare against the cached HMAC key.
+fn bench_single(c: &mut Criterion) {
+    let totp = TOTP::new(SECRET, MacDigest::SHA1, 6, 30);
+    c.bench_function("single get_otp", |b| {
+        b.iter(|| black_box(totp.get_otp(black_box(1_234_567_890))))
+    });
+}
+
+// Measures decoding a large batch of base32 secrets, as when importing
+// accounts.
+fn bench_base32_decode(c: &mut Criterion) {
+    let secrets: Vec<String> = (0..1000)
+        .map(|i| format!("GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ{:A>8}", base32_suffix(i)))
+        .collect();
+    let mut group = c.benchmark_group("base32 decode 1000 secrets");
+    group.bench_function("lookup table", |b| {
+        b.iter(|| {
+            for secret in &secrets {
+                black_box(base32_decode_verbose(black_box(secret)).unwrap());
+            }
+        })
+    });
+    // The `base32` crate decoder, which was used before the lookup table
+    group.bench_function("base32 crate", |b| {
+        b.iter(|| {
+            for secret in &secrets {
+                black_box(
+                    base32::decode(Alphabet::RFC4648 { padding: false }, black_box(secret))
+                        .unwrap(),
+                );
+            }
+        })
+    });
+    group.finish();
+}
+
+// Returns a distinct base32 string of up to 8 characters for each index.
+fn base32_suffix(mut index: usize) -> String {
+    let alphabet = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+    let mut suffix = String::new();
+    while index > 0 {
+        suffix.push(alphabet[index % 32] as char);
+        index /= 32;
+    }
+    suffix
+}
+
+criterion_group!(
+    benches,
+    bench_prepared,
+    bench_batch,
+    bench_single,
+    bench_base32_decode
+);
+criterion_main!(benches);
@@ -0,0 +1,169 @@
+// Importer for unencrypted Aegis Authenticator JSON backups
+
+use serde::Deserialize;
+
+use crate::hotp::HOTP;
+use crate::totp::TOTP;
+use crate::util::{
+    base32_decode, parse_algorithm, validate_digits, validate_period, MacDigest, OtpError,
+    ParseError, ParseResult,
+};
+
+/// The top level of an Aegis backup file.
+#[derive(Deserialize)]
+struct AegisBackup {
+    db: AegisDatabase,
+}
+
+/// The database of an Aegis backup, which is a plain object for
+/// unencrypted backups and a base64-encoded string for encrypted ones.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum AegisDatabase {
+    Plain { entries: Vec<AegisEntry> },
+    // The encrypted contents are only matched to report a clearer error.
+    #[allow(dead_code)]
+    Encrypted(String),
+}
+
+/// A single account in an Aegis backup.
+#[derive(Deserialize)]
+struct AegisEntry {
+    #[serde(rename = "type")]
+    otp_type: String,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    issuer: Option<String>,
+    info: AegisInfo,
+}
+
+/// The generator parameters of an Aegis backup entry.
+#[derive(Deserialize)]
+struct AegisInfo {
+    secret: String,
+    algo: Option<String>,
+    digits: Option<u32>,
+    period: Option<u64>,
+    counter: Option<u64>,
+}
+
+/// An account imported from a backup.
+///
+/// Holds the account's name and issuer along with its generator, or the
+/// reason the generator couldn't be imported, so one unsupported account
+/// doesn't prevent importing the others.
+#[derive(Debug)]
+pub struct ParsedOtp {
+    /// The name of the account, such as an email address.
+    pub name: String,
+
+    /// The issuer of the account, if the backup has a non-empty one.
+    pub issuer: Option<String>,
+
+    /// The generator of the account, or the error that prevented creating
+    /// it.
+    pub otp: Result<ParseResult, ParseError>,
+}
+
+/// Parses an unencrypted [Aegis] JSON backup.
+///
+/// Each entry of the backup is returned as a [`ParsedOtp`], in the same
+/// order as in the backup. As with [`crate::util::parse_otpauth_uri`],
+/// [`ParseResult::HOTP`] entries come with the counter stored in the backup.
+///
+/// An entry that uses an unsupported type (such as `steam`) or algorithm
+/// (such as `MD5`), or a digit count outside of 1 to
+/// [`crate::util::MAX_DIGITS`], is returned with the corresponding
+/// [`ParseError`] instead of failing the whole import.
+///
+/// This method is safe and shouldn't panic. It will return an
+/// [`OtpError::MalformedBackup`] error if the backup is encrypted or isn't
+/// a valid Aegis backup.
+///
+/// [Aegis]: https://github.com/beemdevelopment/Aegis
+pub fn parse_aegis_backup(json: &str) -> Result<Vec<ParsedOtp>, OtpError> {
+    let backup: AegisBackup = match serde_json::from_str(json) {
+        Ok(x) => x,
+        Err(e) => return Err(OtpError::MalformedBackup(e.to_string())),
+    };
+
+    let entries = match backup.db {
+        AegisDatabase::Plain { entries } => entries,
+        AegisDatabase::Encrypted(_) => {
+            return Err(OtpError::MalformedBackup(String::from(
+                "Encrypted backups are not supported",
+            )))
+        }
+    };
+
+    Ok(entries
+        .into_iter()
+        .map(|entry| ParsedOtp {
+            name: entry.name,
+            issuer: entry.issuer.filter(|issuer| !issuer.is_empty()),
+            otp: parse_entry(&entry.otp_type, entry.info),
+        })
+        .collect())
+}
+
+/// Converts the parameters of a single Aegis backup entry into a
+/// [`ParseResult`].
+fn parse_entry(otp_type: &str, info: AegisInfo) -> Result<ParseResult, ParseError> {
+    use ParseError::*;
+
+    let digits = match info.digits {
+        Some(x) => match validate_digits(x) {
+            Ok(()) => x,
+            Err(_) => return Err(WrongDigitNumber(x.to_string())),
+        },
+        None => 6,
+    };
+
+    match otp_type {
+        "totp" => {
+            let algo = match info.algo {
+                Some(x) => match parse_algorithm(&x) {
+                    Some(algo) => algo,
+                    None => return Err(UnknownAlgorithm(x)),
+                },
+                None => MacDigest::SHA1,
+            };
+
+            let period = match info.period {
+                Some(x) => match validate_period(x) {
+                    Ok(()) => x,
+                    Err(_) => return Err(InvalidPeriod(x.to_string())),
+                },
+                None => 30,
+            };
+
+            let secret = decode_secret(info.secret)?;
+            Ok(ParseResult::TOTP(TOTP::from_vec(secret, algo, digits, period)))
+        }
+        "hotp" => {
+            if let Some(x) = info.algo {
+                if parse_algorithm(&x) != Some(MacDigest::SHA1) {
+                    return Err(UnknownAlgorithm(x));
+                }
+            }
+
+            let counter = match info.counter {
+                Some(x) => x,
+                None => return Err(MissingCounter),
+            };
+
+            let secret = decode_secret(info.secret)?;
+            Ok(ParseResult::HOTP(HOTP::from_vec(secret, digits), counter))
+        }
+        x => Err(UnknownOtpType(String::from(x))),
+    }
+}
+
+/// Decodes the base32 secret of an Aegis backup entry.
+fn decode_secret(secret: String) -> Result<Vec<u8>, ParseError> {
+    match base32_decode(&secret) {
+        Some(x) => Ok(x),
+        None => Err(ParseError::SecretParsingError(secret)),
+    }
+}
@@ -1,7 +1,18 @@
 // Implementation of the HOTP standard according to RFC4226 by Tejas Mehta
 
+use std::str::FromStr;
+
+#[cfg(feature = "secrecy")]
+use secrecy::{ExposeSecret, SecretString, SecretVec};
+
 use crate::otp_result::OTPResult;
-use crate::util::{base32_decode, get_code, hash_generic, MacDigest};
+#[cfg(feature = "secrecy")]
+use crate::util::base32_decode_verbose;
+use crate::util::{
+    base32_decode, build_otpauth_uri, get_code, parse_otpauth_uri, truncate, try_get_code,
+    try_truncate, validate_truncation_offset, window_steps, MacDigest, OtpError, ParseError,
+    ParseResult, PreparedMac, SecretBytes, TruncationOffset,
+};
 
 /// A HOTP Generator
 ///
@@ -23,13 +34,19 @@ pub struct HOTP {
     /// The secret key used in the HMAC process.
     ///
     /// Often given as a Base32 key, which can be conveniently initialized
-    /// using the [`HOTP::default_from_base32`] constructor.
-    secret: Vec<u8>,
+    /// using the [`HOTP::default_from_base32`] constructor. With the
+    /// `zeroize` feature, it's wiped from memory when the instance is dropped.
+    secret: SecretBytes,
 
     /// The number of digits of the code generated.
     ///
     /// This value defaults to 6 if not specified in a constructor.
     digits: u32,
+
+    /// The offset of the bytes of the HMAC result the code is computed from.
+    ///
+    /// This value defaults to [`TruncationOffset::Dynamic`].
+    truncation_offset: TruncationOffset,
 }
 
 /// All initializer implementations for the [`HOTP`] struct.
@@ -40,9 +57,19 @@ impl HOTP {
     /// Since only SHA1 was specified in the reference implementation and
     /// RFC specification, there's no need to initialize with a digest object.
     pub fn new(secret: &[u8], digits: u32) -> Self {
+        HOTP::from_vec(secret.to_vec(), digits)
+    }
+
+    /// Creates a new HOTP instance with an owned byte vector of the secret
+    /// and specified digit count.
+    ///
+    /// Unlike [`HOTP::new`], the secret is moved into the instance instead
+    /// of being copied.
+    pub fn from_vec(secret: Vec<u8>, digits: u32) -> Self {
         HOTP {
-            secret: secret.to_vec(),
+            secret: SecretBytes::from(secret),
             digits,
+            truncation_offset: TruncationOffset::default(),
         }
     }
 
@@ -60,7 +87,7 @@ impl HOTP {
     /// base32-encoded.
     pub fn new_from_base32(secret: &str, digits: u32) -> Self {
         let decoded = base32_decode(secret).expect("Failed to decode base32 string");
-        HOTP::new(&decoded, digits)
+        HOTP::from_vec(decoded, digits)
     }
 
     /// Creates a new HOTP instance from a byte-array representation of
@@ -84,14 +111,137 @@ impl HOTP {
     pub fn default_from_base32(secret: &str) -> Self {
         HOTP::new_from_base32(secret, 6)
     }
+
+    /// Creates a new HOTP instance from an `otpauth://hotp/` URI.
+    ///
+    /// Since [`HOTP`] doesn't store a counter, the URI's `counter` parameter
+    /// is returned alongside the generator. Unlike TOTP URIs, HOTP URIs are
+    /// stateful: they're only accurate for as long as the counter they were
+    /// created with is the current one.
+    ///
+    /// This method is safe and shouldn't panic. It will return an error if
+    /// the provided uri is invalid, or is a TOTP uri.
+    pub fn from_uri(uri: &str) -> Result<(Self, u64), ParseError> {
+        match parse_otpauth_uri(uri)? {
+            ParseResult::HOTP(hotp, counter) => Ok((hotp, counter)),
+            ParseResult::TOTP(_) => Err(ParseError::UnexpectedOtpType(String::from("totp"))),
+        }
+    }
+}
+
+/// A FromStr implementation for the [`HOTP`] struct
+///
+/// A string starting with `otpauth://` is parsed as a URI with
+/// [`HOTP::from_uri`], dropping the URI's counter. Callers that need the
+/// counter should use [`HOTP::from_uri`] directly. Any other string is
+/// treated as a bare base32-encoded secret, with the 6 digits of
+/// [`HOTP::default_from_base32`].
+///
+/// Returns a [`ParseError::MissingSecret`] if the string is empty or only
+/// whitespace, and a [`ParseError::SecretParsingError`] if a bare secret
+/// isn't correctly base32-encoded.
+impl FromStr for HOTP {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.starts_with("otpauth://") {
+            return HOTP::from_uri(s).map(|(hotp, _)| hotp);
+        }
+        if s.trim().is_empty() {
+            return Err(ParseError::MissingSecret);
+        }
+        match base32_decode(s) {
+            Some(secret) => Ok(HOTP::from_vec(secret, 6)),
+            None => Err(ParseError::SecretParsingError(String::from(s))),
+        }
+    }
+}
+
+/// All initializers from [`secrecy`] secrets for the [`HOTP`] struct.
+///
+/// These keep the secret wrapped until it's in the instance, so it can't
+/// end up in logs or panic messages on the way.
+#[cfg(feature = "secrecy")]
+impl HOTP {
+    /// Creates a new HOTP instance from a [`SecretVec`] of the secret bytes,
+    /// a digit count.
+    pub fn new_from_secret_vec(secret: &SecretVec<u8>, digits: u32) -> Self {
+        HOTP::from_vec(secret.expose_secret().to_vec(), digits)
+    }
+
+    /// Creates a new HOTP instance from a [`SecretString`] of the
+    /// base32-encoded secret, a digit count.
+    ///
+    /// # Errors
+    /// Returns the error of [`base32_decode_verbose`] if the provided string
+    /// is not correctly base32-encoded.
+    pub fn new_from_secret_base32(secret: &SecretString, digits: u32) -> Result<Self, OtpError> {
+        let decoded = base32_decode_verbose(secret.expose_secret())?;
+        Ok(HOTP::from_vec(decoded, digits))
+    }
+}
+
+/// All modifiers for the [`HOTP`] struct
+impl HOTP {
+    /// Sets the truncation offset used to compute codes, returning the
+    /// updated instance.
+    ///
+    /// Only legacy tokens that don't follow [RFC4226] need this, as the RFC
+    /// mandates [`TruncationOffset::Dynamic`].
+    ///
+    /// # Errors
+    /// Returns [`OtpError::InvalidTruncationOffset`] if a fixed offset
+    /// doesn't leave room for 4 bytes in the (20-byte) SHA1 output.
+    ///
+    /// [RFC4226]: https://datatracker.ietf.org/doc/html/rfc4226
+    pub fn with_truncation_offset(
+        mut self,
+        truncation_offset: TruncationOffset,
+    ) -> Result<Self, OtpError> {
+        validate_truncation_offset(truncation_offset, &MacDigest::SHA1)?;
+        self.truncation_offset = truncation_offset;
+        Ok(self)
+    }
 }
 
 /// All getters for the ['HOTP'] struct
 impl HOTP {
+    /// Gets the secret used for code generation.
+    pub(crate) fn get_secret(&self) -> &[u8] {
+        &self.secret
+    }
+
     /// Gets the number of digits of the code.
     pub fn get_digits(&self) -> u32 {
         self.digits
     }
+
+    /// Gets the truncation offset used to compute codes.
+    pub fn get_truncation_offset(&self) -> TruncationOffset {
+        self.truncation_offset
+    }
+}
+
+/// All otpauth URI methods for the [`HOTP`] struct.
+impl HOTP {
+    /// Returns the `otpauth://hotp/` URI of the generator, for the given
+    /// account label, optional issuer, and current counter value.
+    ///
+    /// The counter is embedded in the URI as the `counter` parameter, and
+    /// should be the next counter value the server expects. As with
+    /// [`HOTP::from_uri`], the URI is only accurate for that counter value.
+    pub fn hotp_to_uri(&self, label: &str, issuer: Option<&str>, counter: u64) -> String {
+        build_otpauth_uri(
+            "hotp",
+            label,
+            issuer,
+            &self.secret,
+            &[
+                ("digits", &self.digits.to_string()),
+                ("counter", &counter.to_string()),
+            ],
+        )
+    }
 }
 
 /// All otp generation methods for the [`HOTP`] struct.
@@ -103,13 +253,210 @@ impl HOTP {
     /// # Panics
     /// This method panics if the hash's secret is incorrectly given.
     pub fn get_otp(&self, counter: u64) -> OTPResult {
-        let hash = hash_generic(&counter.to_be_bytes(), &self.secret, &MacDigest::SHA1);
-        let offset = (hash[hash.len() - 1] & 0xf) as usize;
-        let bytes: [u8; 4] = hash[offset..offset + 4]
-            .try_into()
-            .expect("Failed byte get");
+        self.get_otp_with_mac(&self.prepare_mac(), counter)
+    }
+
+    /// Generates and returns the HOTP value like [`HOTP::get_otp`], returning
+    /// an error instead of panicking on any input.
+    ///
+    /// # Errors
+    /// Returns [`OtpError::InvalidDigits`] if the code can't have that many
+    /// digits, and [`OtpError::HashTooShort`] if the truncation offset
+    /// doesn't fit in the hash.
+    pub fn try_get_otp(&self, counter: u64) -> Result<OTPResult, OtpError> {
+        let hash = self.prepare_mac().hash(&counter.to_be_bytes());
+        let bytes = try_truncate(&hash, &MacDigest::SHA1, self.truncation_offset)?;
+        let code = try_get_code(bytes, self.digits)?;
+        Ok(OTPResult::new(self.digits, code))
+    }
 
+    /// Generates and returns the HOTP values of `count` consecutive counters,
+    /// starting at `start_counter`.
+    ///
+    /// Each code is returned along with its counter. The HMAC is only keyed
+    /// once for all codes, so this is faster than calling [`HOTP::get_otp`]
+    /// for each counter. Fewer codes are returned if the counter would
+    /// overflow.
+    ///
+    /// # Panics
+    /// This method panics if the hash's secret is incorrectly given.
+    pub fn codes(&self, start_counter: u64, count: usize) -> Vec<(u64, OTPResult)> {
+        let mac = self.prepare_mac();
+        (0..count as u64)
+            .map_while(|i| start_counter.checked_add(i))
+            .map(|counter| (counter, self.get_otp_with_mac(&mac, counter)))
+            .collect()
+    }
+
+    /// Checks the given code against the counters from `before` counters
+    /// before to `after` counters after `center_counter`.
+    ///
+    /// The bounds are clamped to the range of a [`u64`], so checking before
+    /// a small counter (for instance when resyncing a client that is behind
+    /// the server) never underflows. Both `before` and `after` are capped at
+    /// [`crate::util::MAX_WINDOW_STEPS`]. Returns the matching counter,
+    /// preferring the counter closest to `center_counter` if more than one
+    /// matches.
+    ///
+    /// Full-width and Arabic-Indic digits in the code are accepted as their
+    /// ASCII equivalents (see [`OTPResult::matches`]).
+    ///
+    /// # Panics
+    /// This method panics if the hash's secret is incorrectly given.
+    pub fn verify_window_bidirectional(
+        &self,
+        code: &str,
+        center_counter: u64,
+        before: u64,
+        after: u64,
+    ) -> Option<u64> {
+        let mac = self.prepare_mac();
+        window_steps(center_counter, after, before)
+            .map(|(_, counter)| counter)
+            .find(|counter| self.get_otp_with_mac(&mac, *counter).matches(code))
+    }
+
+    /// Resynchronizes the counter with two consecutive codes from a client.
+    ///
+    /// Searches the `search_len` counters starting at `search_start` for a
+    /// counter whose code is `code1` and whose next counter's code is
+    /// `code2`, and returns the counter after both as the new counter value.
+    /// Requiring two consecutive matches makes false matches very unlikely,
+    /// so a much larger range can be searched than with a single code.
+    ///
+    /// Returns [`None`] if no pair of counters in the range matches, or if
+    /// the new counter would overflow.
+    ///
+    /// # Panics
+    /// This method panics if the hash's secret is incorrectly given.
+    pub fn resync(
+        &self,
+        code1: &str,
+        code2: &str,
+        search_start: u64,
+        search_len: u64,
+    ) -> Option<u64> {
+        let mac = self.prepare_mac();
+        let end = search_start.saturating_add(search_len);
+        let mut current = self.get_otp_with_mac(&mac, search_start);
+        for counter in search_start..end {
+            let next = self.get_otp_with_mac(&mac, counter.checked_add(1)?);
+            if current.matches(code1) && next.matches(code2) {
+                return counter.checked_add(2);
+            }
+            current = next;
+        }
+        None
+    }
+
+    /// Returns the moving factor of the given counter, which is the message
+    /// the HMAC is computed over.
+    ///
+    /// This is the counter as 8 big-endian bytes. It's only useful to show
+    /// how a code is derived, and has no effect on generation.
+    pub fn moving_factor_bytes(&self, counter: u64) -> [u8; 8] {
+        counter.to_be_bytes()
+    }
+
+    /// Keys a new HMAC instance with the generator's secret.
+    fn prepare_mac(&self) -> PreparedMac {
+        PreparedMac::new(&self.secret, &MacDigest::SHA1)
+    }
+
+    /// Generates and returns the HOTP value for the given counter, using the
+    /// given keyed HMAC instance.
+    fn get_otp_with_mac(&self, mac: &PreparedMac, counter: u64) -> OTPResult {
+        let hash = mac.hash(&counter.to_be_bytes());
+        let bytes = truncate(&hash, &MacDigest::SHA1, self.truncation_offset);
         let code = get_code(bytes, self.digits);
         OTPResult::new(self.digits, code)
     }
 }
+
+/// A window of HOTP counters with replay protection, for a server verifying
+/// the codes of a single token.
+///
+/// The window starts at a base counter, which is the lowest counter that
+/// hasn't been used yet, and tracks which of the following counters have
+/// been used in a bitmask. A code is only accepted once, and a code for a
+/// counter ahead of the base doesn't invalidate the skipped counters, as
+/// their codes may still arrive (for instance from a token shared between
+/// devices).
+#[derive(Debug, Clone, Hash)]
+pub struct HotpWindow {
+    /// The generator codes are checked against.
+    hotp: HOTP,
+
+    /// The lowest counter that hasn't been used.
+    base_counter: u64,
+
+    /// The used counters after the base counter, where bit `i` is the
+    /// counter `base_counter + i`.
+    used: u64,
+}
+
+/// All initializer implementations for the [`HotpWindow`] struct.
+impl HotpWindow {
+    /// Creates a new window for the given generator, where no counter from
+    /// `base_counter` onwards has been used.
+    pub fn new(hotp: HOTP, base_counter: u64) -> Self {
+        HotpWindow {
+            hotp,
+            base_counter,
+            used: 0,
+        }
+    }
+}
+
+/// All getters for the [`HotpWindow`] struct.
+impl HotpWindow {
+    /// Gets the lowest counter that hasn't been used.
+    pub fn get_base_counter(&self) -> u64 {
+        self.base_counter
+    }
+
+    /// Checks whether the given counter has been used, which is the case
+    /// for every counter below the base counter.
+    pub fn is_used(&self, counter: u64) -> bool {
+        match counter.checked_sub(self.base_counter) {
+            None => true,
+            Some(offset) => offset < 64 && self.used & (1 << offset) != 0,
+        }
+    }
+}
+
+/// All verification methods for the [`HotpWindow`] struct.
+impl HotpWindow {
+    /// Checks the given code against the unused counters from the base
+    /// counter to `look_ahead` counters after it, and marks the matching
+    /// counter as used.
+    ///
+    /// The look-ahead is capped at 63 counters, the size of the bitmask.
+    /// After a code is accepted, the base counter is moved past every used
+    /// counter at the start of the window, stopping at [`u64::MAX`]. Returns
+    /// whether the code was accepted, so a replayed code is rejected.
+    ///
+    /// # Panics
+    /// This method panics if the [`HOTP::get_otp`] method does.
+    pub fn verify(&mut self, code: &str, look_ahead: u64) -> bool {
+        let mac = self.hotp.prepare_mac();
+        let matched = (0..=look_ahead.min(63))
+            .filter(|offset| self.used & (1 << offset) == 0)
+            .find(|offset| match self.base_counter.checked_add(*offset) {
+                Some(counter) => self.hotp.get_otp_with_mac(&mac, counter).matches(code),
+                None => false,
+            });
+        let offset = match matched {
+            Some(offset) => offset,
+            None => return false,
+        };
+
+        self.used |= 1 << offset;
+        // The base can't move past u64::MAX, so the bits of the counters it
+        // can't move past are kept to still reject them
+        let slide = (self.used.trailing_ones() as u64).min(u64::MAX - self.base_counter);
+        self.base_counter += slide;
+        self.used = self.used.checked_shr(slide as u32).unwrap_or(0);
+        true
+    }
+}
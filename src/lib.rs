@@ -80,4 +80,9 @@
 pub mod hotp;
 pub mod totp;
 pub mod util;
-pub mod otp_result;
\ No newline at end of file
+pub mod otp_result;
+
+#[cfg(feature = "serde")]
+pub mod aegis;
+#[cfg(feature = "wasm")]
+pub mod wasm;
@@ -1,6 +1,11 @@
 use std::fmt;
 use std::fmt::Formatter;
 
+use crate::util::OtpError;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
 /// A convenience struct to hold the result of a [`HOTP`] or [`TOTP`]
 /// generation.
 ///
@@ -8,21 +13,101 @@ use std::fmt::Formatter;
 /// which will be equal to or less than the digit count. Currently houses
 /// a convenience [`OTPResult::as_string`] which returns a zero-padded string
 /// that has a length of [`OTPResult::digits`]. Additionally, the numerical
-/// representation of the code can be got with [`OTPResult::as_u32`].
+/// representation of the code can be got with [`OTPResult::as_u64`].
 ///
 /// Returned as a result of either [`HOTP::get_otp`], [`TOTP::get_otp`]
 /// or [`TOTP::get_otp_with_custom_time_start`].
+///
+/// With the `serde` feature, the struct is serialized with both the numeric
+/// code and the zero-padded string, as in
+/// `{ "code": 42315, "digits": 6, "formatted": "042315" }`. Deserialization
+/// only uses `code` and `digits`. Results with a radix other than 10 also
+/// have a `radix` field.
+///
+/// Two results are equal (`==`) only if their code, digit count and radix
+/// are all equal, as they would then be displayed the same way. To only
+/// compare the numeric codes, use [`OTPResult::same_code_as`].
 #[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(into = "SerializedOTPResult", try_from = "SerializedOTPResult")
+)]
 pub struct OTPResult {
     digits: u32,
-    code: u32,
+    code: u64,
+    radix: u32,
+}
+
+/// The serialized form of an [`OTPResult`].
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+struct SerializedOTPResult {
+    code: u64,
+    digits: u32,
+    #[serde(default = "decimal_radix", skip_serializing_if = "is_decimal_radix")]
+    radix: u32,
+    #[serde(default, skip_deserializing)]
+    formatted: String,
+}
+
+#[cfg(feature = "serde")]
+fn decimal_radix() -> u32 {
+    10
+}
+
+#[cfg(feature = "serde")]
+fn is_decimal_radix(radix: &u32) -> bool {
+    *radix == 10
+}
+
+#[cfg(feature = "serde")]
+impl From<OTPResult> for SerializedOTPResult {
+    fn from(result: OTPResult) -> Self {
+        SerializedOTPResult {
+            code: result.code,
+            digits: result.digits,
+            radix: result.radix,
+            formatted: result.as_string(),
+        }
+    }
+}
+
+// Rejects an out-of-range radix instead of panicking in `new_with_radix`,
+// as the serialized form can come from untrusted input.
+#[cfg(feature = "serde")]
+impl TryFrom<SerializedOTPResult> for OTPResult {
+    type Error = String;
+
+    fn try_from(result: SerializedOTPResult) -> Result<Self, Self::Error> {
+        if !(2..=36).contains(&result.radix) {
+            return Err(format!("Radix must be between 2 and 36, got {}", result.radix));
+        }
+        Ok(OTPResult::new_with_radix(result.digits, result.code, result.radix))
+    }
 }
 
 /// Constructors for the [`OTPResult`] struct.
 impl OTPResult {
     /// Creates a new instance with the provided digit count and OTP code.
-    pub fn new(digits: u32, code: u32 ) -> Self {
-        OTPResult { digits, code }
+    ///
+    /// The code is displayed in decimal.
+    pub fn new(digits: u32, code: u64) -> Self {
+        OTPResult::new_with_radix(digits, code, 10)
+    }
+
+    /// Creates a new instance with the provided digit count, OTP code, and
+    /// radix the code is displayed in.
+    ///
+    /// # Panics
+    /// This method panics if the radix isn't between 2 and 36.
+    pub fn new_with_radix(digits: u32, code: u64, radix: u32) -> Self {
+        assert!((2..=36).contains(&radix), "Radix must be between 2 and 36");
+        OTPResult {
+            digits,
+            code,
+            radix,
+        }
     }
 }
 
@@ -32,6 +117,11 @@ impl OTPResult {
     ///
     /// Also the count used to determine how long the formatted string will be.
     pub fn get_digits(&self) -> u32 { self.digits }
+
+    /// Gets the radix the code is displayed in.
+    ///
+    /// This is 10 unless the struct was created with a different radix.
+    pub fn get_radix(&self) -> u32 { self.radix }
 }
 
 /// Convenience code getters for the [`OTPResult`] struct
@@ -40,17 +130,141 @@ impl OTPResult {
     ///
     /// If [`OTPResult::code`] is less than [`OTPResult::digits`] long, leading zeroes
     /// will be added to the string.
+    ///
+    /// The code is displayed in the result's radix (see
+    /// [`OTPResult::get_radix`]), which is decimal by default.
     pub fn as_string(&self) -> String {
-        format!("{:01$}", self.code as usize, self.digits as usize)
+        self.as_radix_string(self.radix)
+    }
+
+    /// Returns the OTP displayed in the given radix, zero-padded to a length
+    /// of at least [`OTPResult.digits`].
+    ///
+    /// Digits above 9 are displayed as lowercase letters.
+    ///
+    /// # Panics
+    /// This method panics if the radix isn't between 2 and 36.
+    pub fn as_radix_string(&self, radix: u32) -> String {
+        assert!((2..=36).contains(&radix), "Radix must be between 2 and 36");
+        let mut chars = Vec::new();
+        let mut code = self.code;
+        while code > 0 || chars.len() < self.digits as usize {
+            let digit = (code % radix as u64) as u32;
+            chars.push(std::char::from_digit(digit, radix).expect("Failed digit conversion"));
+            code /= radix as u64;
+        }
+        chars.iter().rev().collect()
+    }
+
+    /// Returns the OTP as a zero-padded string grouped for readability.
+    ///
+    /// Codes with an even digit count are split in half by a space, like
+    /// most authenticator applications display them (`123 456` for 6 digits
+    /// or `1234 5678` for 8 digits). Codes with an odd digit count are
+    /// returned the same as [`OTPResult::as_string`].
+    pub fn formatted(&self) -> String {
+        let code = self.as_string();
+        match self.digits % 2 {
+            0 => {
+                let (first, second) = code.split_at(code.len() / 2);
+                format!("{} {}", first, second)
+            }
+            _ => code,
+        }
+    }
+
+
+    /// Writes the OTP as zero-padded ASCII digits (in the result's radix) into
+    /// the given buffer, without allocating.
+    ///
+    /// The written digits are the same as [`OTPResult::as_string`], and start
+    /// at the beginning of the buffer. Returns the number of bytes written,
+    /// which is [`OTPResult::digits`], or more if the code itself has more
+    /// digits than that.
+    ///
+    /// # Errors
+    /// Returns [`OtpError::BufferTooSmall`] if the buffer is shorter than the
+    /// number of digits to write. The buffer is left untouched in that case.
+    pub fn write_digits(&self, buf: &mut [u8]) -> Result<usize, OtpError> {
+        let radix = self.radix as u64;
+        let mut code_digits = 1;
+        let mut rest = self.code / radix;
+        while rest > 0 {
+            code_digits += 1;
+            rest /= radix;
+        }
+        let needed = code_digits.max(self.digits as usize);
+        if buf.len() < needed {
+            return Err(OtpError::BufferTooSmall {
+                needed,
+                available: buf.len(),
+            });
+        }
+        let mut code = self.code;
+        for byte in buf[..needed].iter_mut().rev() {
+            let digit = (code % radix) as u32;
+            *byte = std::char::from_digit(digit, self.radix).expect("Failed digit conversion") as u8;
+            code /= radix;
+        }
+        Ok(needed)
     }
 
+    /// Checks whether the given code input is this result's code.
+    ///
+    /// The input is compared to [`OTPResult::as_string`] after converting
+    /// full-width (`０`-`９`), Arabic-Indic (`٠`-`٩`) and Eastern
+    /// Arabic-Indic (`۰`-`۹`) digits to ASCII digits, as some mobile
+    /// keyboards enter those instead of the ASCII digits they look like.
+    ///
+    /// The comparison runs in constant time for inputs of the code's length,
+    /// so it doesn't leak how many leading digits of a guess are correct.
+    pub fn matches(&self, code: &str) -> bool {
+        constant_time_eq(normalize_digits(code).as_bytes(), self.as_string().as_bytes())
+    }
+
+    /// Checks whether this result has the same numeric code as another one.
+    ///
+    /// Unlike `==`, the digit count and radix are ignored, so a code of
+    /// 42315 with 6 digits (`042315`) and one with 8 digits (`00042315`)
+    /// have the same code.
+    pub fn same_code_as(&self, other: &OTPResult) -> bool {
+        self.code == other.code
+    }
 
     /// Returns the OTP as it's original numerical representation
     ///
     /// This number may not be [`OTPResult::digits`] long.
-    pub fn as_u32(&self) -> u32 {
+    pub fn as_u64(&self) -> u64 {
         self.code
     }
+
+    /// Returns the OTP as it's original numerical representation, as a
+    /// [`u32`].
+    ///
+    /// This number may not be [`OTPResult::digits`] long. Codes generated
+    /// by [`HOTP`](crate::hotp::HOTP) and [`TOTP`](crate::totp::TOTP) always fit in a [`u32`], but codes created
+    /// through [`OTPResult::new`] are truncated if they don't.
+    pub fn as_u32(&self) -> u32 {
+        self.code as u32
+    }
+}
+
+/// Converts the full-width and Arabic-Indic digits of a code input to ASCII
+/// digits, leaving any other character as is.
+pub(crate) fn normalize_digits(code: &str) -> String {
+    code.chars()
+        .map(|c| match c {
+            '\u{FF10}'..='\u{FF19}' => ascii_digit(c as u32 - 0xFF10),
+            '\u{0660}'..='\u{0669}' => ascii_digit(c as u32 - 0x0660),
+            '\u{06F0}'..='\u{06F9}' => ascii_digit(c as u32 - 0x06F0),
+            _ => c,
+        })
+        .collect()
+}
+
+/// Returns the ASCII digit of a value between 0 and 9.
+fn ascii_digit(value: u32) -> char {
+    std::char::from_digit(value, 10).expect("Failed digit conversion")
 }
 
 /// A Display implementation for the [`OTPResult`] struct
@@ -61,4 +275,17 @@ impl fmt::Display for OTPResult {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
        write!(f, "{}", self.as_string())
     }
-}
\ No newline at end of file
+}
+/// Compares two byte strings in constant time for equal lengths.
+///
+/// Every byte is compared, instead of stopping at the first difference like
+/// `==`, so comparing a submitted code to a generated one doesn't leak how
+/// much of it is correct. The length isn't secret (it's the digit count),
+/// so inputs of different lengths are rejected right away.
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let diff = a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y));
+    std::hint::black_box(diff) == 0
+}
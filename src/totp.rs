@@ -1,5 +1,24 @@
-use crate::otp_result::OTPResult;
-use crate::util::{base32_decode, get_code, hash_generic, MacDigest};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[cfg(feature = "secrecy")]
+use secrecy::{ExposeSecret, SecretString, SecretVec};
+
+use crate::otp_result::{constant_time_eq, normalize_digits, OTPResult};
+#[cfg(feature = "mac-cache")]
+use crate::util::MacCache;
+#[cfg(feature = "calendar")]
+use crate::util::utc_to_unix;
+use crate::util::{
+    algorithm_name, base32_decode, base32_decode_verbose, base32_encode, build_otpauth_uri,
+    get_code, get_code_with_radix, parse_algorithm, parse_otpauth_uri, resolve_truncation_offset,
+    truncate, truncate_extended, try_get_code, try_truncate, validate_digits, validate_period,
+    validate_secret_length, validate_truncation_offset, window_steps, Base32Secret, MacDigest,
+    OtpError, ParseError, ParseResult, PreparedMac, SecretBytes, SecretInput, TruncationOffset,
+    MAX_WINDOW_STEPS,
+};
 
 /// A TOTP generator
 ///
@@ -21,8 +40,9 @@ pub struct TOTP {
     /// The secret key used in the HMAC process.
     ///
     /// Often given as a Base32 key, which can be conveniently initialized
-    /// using the [`TOTP::default_from_base32`] constructor.
-    secret: Vec<u8>,
+    /// using the [`TOTP::default_from_base32`] constructor. With the
+    /// `zeroize` feature, it's wiped from memory when the instance is dropped.
+    secret: SecretBytes,
 
     /// The digest to use in the HMAC process.
     ///
@@ -39,6 +59,169 @@ pub struct TOTP {
     ///
     /// This value defaults to 30 if not specified in a constructor.
     period: u64,
+
+    /// The epoch (T0 in [RFC6238]) in unix seconds from which time-steps
+    /// are counted.
+    ///
+    /// This is part of the generator's configuration and applies to every
+    /// generated code, unlike the `time_start` given to methods such as
+    /// [`TOTP::get_otp_with_custom_time_start`], which is a per-call offset
+    /// applied on top of it.
+    ///
+    /// This value defaults to 0 (the unix epoch) if not specified in a
+    /// constructor.
+    ///
+    /// [RFC6238]: https://datatracker.ietf.org/doc/html/rfc6238
+    epoch: u64,
+
+    /// What to do when a time before the epoch (and start time) is given.
+    ///
+    /// This value defaults to [`UnderflowPolicy::Error`].
+    underflow_policy: UnderflowPolicy,
+
+    /// The offset of the bytes of the HMAC result the code is computed from.
+    ///
+    /// This value defaults to [`TruncationOffset::Dynamic`].
+    truncation_offset: TruncationOffset,
+
+    /// The HMAC instance keyed with the secret, cached on first use.
+    ///
+    /// Unlike the secret, it isn't wiped from memory by the `zeroize`
+    /// feature.
+    #[cfg(feature = "mac-cache")]
+    mac_cache: MacCache,
+}
+
+/// The behavior of a [`TOTP`] generator when given a time before its epoch
+/// (plus the start time, when one is given).
+///
+/// The elapsed time can't be negative, so such a time either has to be
+/// treated as an error or clamped to the epoch.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, Default)]
+pub enum UnderflowPolicy {
+    /// Treat the time as an error. Methods returning a code directly
+    /// panic.
+    #[default]
+    Error,
+
+    /// Clamp the time to the epoch, so the first time-step is used.
+    Saturate,
+}
+
+/// The validity of a code relative to the time-step it was checked at.
+///
+/// Returned by [`TOTP::classify`], which only checks the adjacent steps.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub enum CodeValidity {
+    /// The code is the one of the current time-step.
+    Current,
+
+    /// The code is the one of the previous time-step, so it just expired.
+    Previous,
+
+    /// The code is the one of the next time-step, which usually means the
+    /// client's clock is ahead.
+    Next,
+
+    /// The code isn't the one of any of the three time-steps.
+    Invalid,
+}
+
+/// An advisory warning about an unusual [`TOTP`] configuration.
+///
+/// Returned by [`TOTP::lint`]. These aren't errors, as the configuration
+/// still generates valid codes, but are worth pointing out to a user
+/// choosing the configuration. Each warning has a stable identifier
+/// ([`ConfigWarning::code`]) and a human-readable message
+/// ([`ConfigWarning::message`], also used by its [`Display`] implementation).
+///
+/// [`Display`]: fmt::Display
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub enum ConfigWarning {
+    /// The period (in seconds) is shorter than 15 seconds.
+    ShortPeriod(u64),
+
+    /// The code has fewer than 6 digits.
+    FewDigits(u32),
+
+    /// The code has more than 8 digits.
+    ManyDigits(u32),
+
+    /// The digest isn't [`MacDigest::SHA1`].
+    UncommonDigest(MacDigest),
+}
+
+/// All getters for the [`ConfigWarning`] enum
+impl ConfigWarning {
+    /// Returns the stable identifier of the warning, such as
+    /// `"short_period"`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ConfigWarning::ShortPeriod(_) => "short_period",
+            ConfigWarning::FewDigits(_) => "few_digits",
+            ConfigWarning::ManyDigits(_) => "many_digits",
+            ConfigWarning::UncommonDigest(_) => "uncommon_digest",
+        }
+    }
+
+    /// Returns a human-readable description of the warning.
+    pub fn message(&self) -> String {
+        match self {
+            ConfigWarning::ShortPeriod(period) => format!(
+                "A period of {} seconds is unusually short and leaves little time to enter codes",
+                period
+            ),
+            ConfigWarning::FewDigits(digits) => format!(
+                "Codes of {} digits are easier to guess than the usual 6 digits",
+                digits
+            ),
+            ConfigWarning::ManyDigits(digits) => format!(
+                "Codes of {} digits are harder to enter and aren't supported by all authenticator apps",
+                digits
+            ),
+            ConfigWarning::UncommonDigest(digest) => format!(
+                "The {} algorithm isn't supported by all authenticator apps",
+                algorithm_name(digest)
+            ),
+        }
+    }
+}
+
+/// A Display implementation for the [`ConfigWarning`] enum
+///
+/// Shows the warning's message.
+impl fmt::Display for ConfigWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message())
+    }
+}
+
+/// The configuration of a [`TOTP`] generator, without its secret.
+///
+/// This is a view of a generator's parameters that is safe to log or
+/// report in metrics. It can be created from a generator with
+/// [`TotpConfig::from`], and is serializable with the `serde` feature.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TotpConfig {
+    /// The number of digits of the code generated.
+    pub digits: u32,
+
+    /// The period in seconds between two different generated code.
+    pub period: u64,
+
+    /// The digest used in the HMAC process.
+    pub algorithm: MacDigest,
+}
+
+impl From<&TOTP> for TotpConfig {
+    fn from(totp: &TOTP) -> Self {
+        TotpConfig {
+            digits: totp.digits,
+            period: totp.period,
+            algorithm: totp.mac_digest,
+        }
+    }
 }
 
 /// All initializer implementations for the [`TOTP`] struct
@@ -47,14 +230,179 @@ impl TOTP {
     /// secret, a digest algorithm, a number of digits,
     /// and a period in seconds.
     pub fn new(secret: &[u8], mac_digest: MacDigest, digits: u32, period: u64) -> Self {
+        TOTP::from_vec(secret.to_vec(), mac_digest, digits, period)
+    }
+
+    /// Generates a new TOTP instance from an owned byte vector of the
+    /// secret, a digest algorithm, a number of digits,
+    /// and a period in seconds.
+    ///
+    /// Unlike [`TOTP::new`], the secret is moved into the instance instead
+    /// of being copied.
+    pub fn from_vec(secret: Vec<u8>, mac_digest: MacDigest, digits: u32, period: u64) -> Self {
+        TOTP::from_parts(secret, mac_digest, digits, period, 0)
+    }
+
+    /// Generates a new TOTP instance from a byte array representation of the
+    /// secret, a digest algorithm, a number of digits,
+    /// and a period in seconds.
+    ///
+    /// Unlike [`TOTP::new`], the digit count and period are validated. This
+    /// is the constructor to use for arbitrary digit counts (the common 6
+    /// and 8 digit cases are covered by [`TOTP::new_6_digit`] and
+    /// [`TOTP::new_8_digit`]).
+    ///
+    /// # Errors
+    /// Returns [`OtpError::InvalidDigits`] if the digit count isn't between
+    /// 1 and [`crate::util::MAX_DIGITS`], and [`OtpError::InvalidPeriod`]
+    /// if the period is 0.
+    pub fn try_new(
+        secret: &[u8],
+        mac_digest: MacDigest,
+        digits: u32,
+        period: u64,
+    ) -> Result<Self, OtpError> {
+        validate_digits(digits)?;
+        validate_period(period)?;
+        Ok(TOTP::new(secret, mac_digest, digits, period))
+    }
+
+    /// Generates a new TOTP instance like [`TOTP::try_new`], additionally
+    /// checking that the secret is at least as long as the digest's output
+    /// (20, 32 or 64 bytes, see [`MacDigest::output_len`]).
+    ///
+    /// A shorter HMAC key still works, but [RFC6238] recommends keys of the
+    /// digest's output length, and a short key with SHA256 or SHA512 is
+    /// often a sign of a misconfigured secret (such as a SHA1-sized secret
+    /// paired with the wrong digest).
+    ///
+    /// # Errors
+    /// Returns the errors of [`TOTP::try_new`], and
+    /// [`OtpError::SecretTooShort`] if the secret is shorter than the
+    /// digest's output.
+    ///
+    /// [RFC6238]: https://datatracker.ietf.org/doc/html/rfc6238
+    pub fn try_new_strict(
+        secret: &[u8],
+        mac_digest: MacDigest,
+        digits: u32,
+        period: u64,
+    ) -> Result<Self, OtpError> {
+        validate_secret_length(secret, &mac_digest)?;
+        TOTP::try_new(secret, mac_digest, digits, period)
+    }
+
+    /// Generates a new TOTP instance like [`TOTP::try_new`], additionally
+    /// checking that the digest is in the given allow-list.
+    ///
+    /// This allows deployments whose security profile forbids some digests
+    /// (such as SHA1) to reject them when the generator is created.
+    ///
+    /// # Errors
+    /// Returns [`OtpError::DisallowedAlgorithm`] with the digest if it isn't
+    /// in `allowed`, and the errors of [`TOTP::try_new`].
+    pub fn new_with_allowed_digests(
+        secret: &[u8],
+        mac_digest: MacDigest,
+        digits: u32,
+        period: u64,
+        allowed: &[MacDigest],
+    ) -> Result<Self, OtpError> {
+        if !allowed.contains(&mac_digest) {
+            return Err(OtpError::DisallowedAlgorithm(mac_digest));
+        }
+        TOTP::try_new(secret, mac_digest, digits, period)
+    }
+
+    /// Generates a new TOTP instance generating 6-digit codes from a byte
+    /// array representation of the secret, a digest algorithm,
+    /// and a period in seconds.
+    pub fn new_6_digit(secret: &[u8], mac_digest: MacDigest, period: u64) -> Self {
+        TOTP::new(secret, mac_digest, 6, period)
+    }
+
+    /// Generates a new TOTP instance generating 8-digit codes from a byte
+    /// array representation of the secret, a digest algorithm,
+    /// and a period in seconds.
+    pub fn new_8_digit(secret: &[u8], mac_digest: MacDigest, period: u64) -> Self {
+        TOTP::new(secret, mac_digest, 8, period)
+    }
+
+    /// Generates a new TOTP instance from a byte array representation of the
+    /// secret, a digest algorithm, a number of digits, a period in seconds
+    /// and an epoch (T0) in unix seconds.
+    ///
+    /// The epoch is the point in time from which all time-steps are counted
+    /// and is stored with the generator. This differs from the `time_start`
+    /// of [`TOTP::get_otp_with_custom_time_start`], which is only applied to
+    /// that single call (on top of the epoch).
+    pub fn new_with_epoch(
+        secret: &[u8],
+        mac_digest: MacDigest,
+        digits: u32,
+        period: u64,
+        epoch: u64,
+    ) -> Self {
+        TOTP::from_parts(secret.to_vec(), mac_digest, digits, period, epoch)
+    }
+
+    /// Creates a new TOTP instance from all of its parameters, taking
+    /// ownership of the secret.
+    fn from_parts(
+        secret: Vec<u8>,
+        mac_digest: MacDigest,
+        digits: u32,
+        period: u64,
+        epoch: u64,
+    ) -> Self {
         TOTP {
-            secret: secret.to_vec(),
+            secret: SecretBytes::from(secret),
             mac_digest,
             digits,
             period,
+            epoch,
+            underflow_policy: UnderflowPolicy::default(),
+            truncation_offset: TruncationOffset::default(),
+            #[cfg(feature = "mac-cache")]
+            mac_cache: MacCache::default(),
         }
     }
 
+    /// Creates a TOTP instance seeded with the well-known [RFC6238] test
+    /// secret (`"12345678901234567890"`), with the defaults of a SHA1 digest,
+    /// a 6-digit OTP output and a 30-second period.
+    ///
+    /// This gives examples and tests a deterministic generator. It's only
+    /// available with the `testing` feature (or in this crate's own tests).
+    ///
+    /// **This must never be used in production**, as the secret is public.
+    ///
+    /// [RFC6238]: https://datatracker.ietf.org/doc/html/rfc6238
+    #[cfg(any(test, feature = "testing"))]
+    pub fn demo() -> Self {
+        TOTP::default_from_utf8("12345678901234567890")
+    }
+
+    /// Generates a new TOTP instance from a secret in an explicit encoding,
+    /// a digest algorithm, a number of digits, and a period in seconds.
+    ///
+    /// The secret is wrapped in a [`Utf8Secret`](crate::util::Utf8Secret),
+    /// [`Base32Secret`] or [`RawSecret`](crate::util::RawSecret), so a
+    /// base32 secret can't be mistaken for a UTF-8 one.
+    ///
+    /// # Errors
+    /// Returns the error of [`SecretInput::to_bytes`] if a base32 secret is
+    /// not correctly encoded.
+    pub fn from_secret<'a>(
+        secret: impl Into<SecretInput<'a>>,
+        mac_digest: MacDigest,
+        digits: u32,
+        period: u64,
+    ) -> Result<Self, OtpError> {
+        let secret = secret.into().to_bytes()?;
+        Ok(TOTP::from_vec(secret, mac_digest, digits, period))
+    }
+
     /// Generates a new TOTP instance from an utf8 representation of the
     /// secret, a digest algorithm, a number of digits,
     /// and a period in seconds.
@@ -69,8 +417,8 @@ impl TOTP {
     /// # Panics
     /// This method panics if the provided string is not correctly base32 encoded.
     pub fn new_from_base32(secret: &str, mac_digest: MacDigest, digits: u32, period: u64) -> Self {
-        let decoded = base32_decode(secret).expect("Failed to decode base32 string");
-        TOTP::new(&decoded, mac_digest, digits, period)
+        TOTP::from_secret(Base32Secret(secret), mac_digest, digits, period)
+            .expect("Failed to decode base32 string")
     }
 
     /// Creates a new TOTP instance with a byte-array representation of the
@@ -129,10 +477,117 @@ impl TOTP {
     pub fn default_from_base32_with_digest(secret: &str, mac_digest: MacDigest) -> Self {
         TOTP::new_from_base32(secret, mac_digest, 6, 30)
     }
+
+    /// Creates a new TOTP instance from an `otpauth://totp/` URI.
+    ///
+    /// This method is safe and shouldn't panic. It will return an error if
+    /// the provided uri is invalid, or is a HOTP uri.
+    pub fn from_uri(uri: &str) -> Result<Self, ParseError> {
+        match parse_otpauth_uri(uri)? {
+            ParseResult::TOTP(totp) => Ok(totp),
+            ParseResult::HOTP(..) => Err(ParseError::UnexpectedOtpType(String::from("hotp"))),
+        }
+    }
+}
+
+/// A FromStr implementation for the [`TOTP`] struct
+///
+/// A string starting with `otpauth://` is parsed as a URI with
+/// [`TOTP::from_uri`]. Any other string is treated as a bare base32-encoded
+/// secret, with the defaults of [`TOTP::default_from_base32`] (SHA1, 6 digits
+/// and a 30-second period).
+///
+/// Returns a [`ParseError::MissingSecret`] if the string is empty or only
+/// whitespace, and a [`ParseError::SecretParsingError`] if a bare secret
+/// isn't correctly base32-encoded.
+impl FromStr for TOTP {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.starts_with("otpauth://") {
+            return TOTP::from_uri(s);
+        }
+        if s.trim().is_empty() {
+            return Err(ParseError::MissingSecret);
+        }
+        match base32_decode(s) {
+            Some(secret) => Ok(TOTP::from_vec(secret, MacDigest::SHA1, 6, 30)),
+            None => Err(ParseError::SecretParsingError(String::from(s))),
+        }
+    }
+}
+
+/// All initializers from [`secrecy`] secrets for the [`TOTP`] struct.
+///
+/// These keep the secret wrapped until it's in the instance, so it can't
+/// end up in logs or panic messages on the way.
+#[cfg(feature = "secrecy")]
+impl TOTP {
+    /// Creates a new TOTP instance from a [`SecretVec`] of the secret bytes,
+    /// a digest algorithm, a number of digits, and a period in seconds.
+    pub fn new_from_secret_vec(
+        secret: &SecretVec<u8>,
+        mac_digest: MacDigest,
+        digits: u32,
+        period: u64,
+    ) -> Self {
+        TOTP::from_vec(secret.expose_secret().to_vec(), mac_digest, digits, period)
+    }
+
+    /// Creates a new TOTP instance from a [`SecretString`] of the
+    /// base32-encoded secret, a digest algorithm, a number of digits, and a period in seconds.
+    ///
+    /// # Errors
+    /// Returns the error of [`base32_decode_verbose`] if the provided string
+    /// is not correctly base32-encoded.
+    pub fn new_from_secret_base32(
+        secret: &SecretString,
+        mac_digest: MacDigest,
+        digits: u32,
+        period: u64,
+    ) -> Result<Self, OtpError> {
+        let decoded = base32_decode_verbose(secret.expose_secret())?;
+        Ok(TOTP::from_vec(decoded, mac_digest, digits, period))
+    }
+}
+
+/// All modifiers for the [`TOTP`] struct
+impl TOTP {
+    /// Sets the policy to apply when a time before the epoch is given,
+    /// returning the updated instance.
+    pub fn with_underflow_policy(mut self, underflow_policy: UnderflowPolicy) -> Self {
+        self.underflow_policy = underflow_policy;
+        self
+    }
+
+    /// Sets the truncation offset used to compute codes, returning the
+    /// updated instance.
+    ///
+    /// Only legacy tokens that don't follow [RFC6238] need this, as the RFC
+    /// mandates [`TruncationOffset::Dynamic`].
+    ///
+    /// # Errors
+    /// Returns [`OtpError::InvalidTruncationOffset`] if a fixed offset
+    /// doesn't leave room for 4 bytes in the output of the digest.
+    ///
+    /// [RFC6238]: https://datatracker.ietf.org/doc/html/rfc6238
+    pub fn with_truncation_offset(
+        mut self,
+        truncation_offset: TruncationOffset,
+    ) -> Result<Self, OtpError> {
+        validate_truncation_offset(truncation_offset, &self.mac_digest)?;
+        self.truncation_offset = truncation_offset;
+        Ok(self)
+    }
 }
 
 /// All getters for the [`TOTP`] struct
 impl TOTP {
+    /// Gets the secret used for code generation.
+    pub(crate) fn get_secret(&self) -> &[u8] {
+        &self.secret
+    }
+
     /// Gets the algorithm used for code generation.
     pub fn get_digest(&self) -> MacDigest {
         self.mac_digest
@@ -147,10 +602,161 @@ impl TOTP {
     pub fn get_period(&self) -> u64 {
         self.period
     }
+
+    /// Gets the epoch (T0) from which time-steps are counted.
+    pub fn get_epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// Gets the policy applied when a time before the epoch is given.
+    pub fn get_underflow_policy(&self) -> UnderflowPolicy {
+        self.underflow_policy
+    }
+
+    /// Gets the truncation offset used to compute codes.
+    pub fn get_truncation_offset(&self) -> TruncationOffset {
+        self.truncation_offset
+    }
+}
+
+/// All otpauth URI methods for the [`TOTP`] struct.
+impl TOTP {
+    /// Returns the `otpauth://totp/` URI of the generator, for the given
+    /// account label and optional issuer.
+    ///
+    /// The URI contains the `algorithm`, `digits` and `period` parameters.
+    /// The epoch and truncation offset of the generator aren't part of the
+    /// otpauth format, and aren't included.
+    pub fn to_uri(&self, label: &str, issuer: Option<&str>) -> String {
+        self.to_uri_with_params(label, issuer, &[])
+    }
+
+    /// Returns the `otpauth://totp/` URI of the generator like
+    /// [`TOTP::to_uri`], with extra app-specific parameters (like `image`).
+    ///
+    /// The extra parameters are percent-encoded, and appended in the given
+    /// order after the standard ones. They aren't checked against the
+    /// standard parameters, so passing one of those again duplicates it.
+    pub fn to_uri_with_params(
+        &self,
+        label: &str,
+        issuer: Option<&str>,
+        extra: &[(&str, &str)],
+    ) -> String {
+        let digits = self.digits.to_string();
+        let period = self.period.to_string();
+        let mut params = vec![
+            ("algorithm", algorithm_name(&self.mac_digest)),
+            ("digits", digits.as_str()),
+            ("period", period.as_str()),
+        ];
+        params.extend_from_slice(extra);
+        build_otpauth_uri("totp", label, issuer, &self.secret, &params)
+    }
+}
+
+/// All parameter map conversions for the [`TOTP`] struct.
+impl TOTP {
+    /// Returns the generator's parameters as a flat list of key-value pairs,
+    /// for storage in config backends without serde.
+    ///
+    /// The keys are `secret_base32`, `algorithm`, `digits` and `period`, in
+    /// that order, followed by `epoch` if it isn't 0. The truncation offset
+    /// and underflow policy aren't included.
+    pub fn to_params(&self) -> Vec<(String, String)> {
+        let mut params = vec![
+            (String::from("secret_base32"), base32_encode(&self.secret)),
+            (
+                String::from("algorithm"),
+                String::from(algorithm_name(&self.mac_digest)),
+            ),
+            (String::from("digits"), self.digits.to_string()),
+            (String::from("period"), self.period.to_string()),
+        ];
+        if self.epoch != 0 {
+            params.push((String::from("epoch"), self.epoch.to_string()));
+        }
+        params
+    }
+
+    /// Creates a new TOTP instance from the key-value pairs returned by
+    /// [`TOTP::to_params`].
+    ///
+    /// The `epoch` key is optional and defaults to 0. Unknown keys are
+    /// ignored, and the first value of a repeated key is used.
+    ///
+    /// # Errors
+    /// Returns [`OtpError::MissingParam`] with the key of a missing
+    /// parameter (or of an empty secret), [`OtpError::InvalidParam`] with the key of a parameter
+    /// that can't be parsed, the error of [`base32_decode_verbose`] if the
+    /// secret isn't correctly base32-encoded, and the errors of
+    /// [`TOTP::try_new`] for an invalid digit count or period.
+    ///
+    /// [`base32_decode_verbose`]: crate::util::base32_decode_verbose
+    pub fn from_params(params: &[(String, String)]) -> Result<Self, OtpError> {
+        let get = |key: &str| {
+            params
+                .iter()
+                .find(|(k, _)| k == key)
+                .map(|(_, v)| v.as_str())
+        };
+        let require = |key: &str| {
+            get(key).ok_or_else(|| OtpError::MissingParam(String::from(key)))
+        };
+        let parse_number = |key: &str, value: &str| {
+            value
+                .parse::<u64>()
+                .map_err(|_| OtpError::InvalidParam(String::from(key)))
+        };
+
+        let secret = require("secret_base32")?;
+        if secret.trim().is_empty() {
+            return Err(OtpError::MissingParam(String::from("secret_base32")));
+        }
+        let mac_digest = parse_algorithm(require("algorithm")?)
+            .ok_or_else(|| OtpError::InvalidParam(String::from("algorithm")))?;
+        let digits = u32::try_from(parse_number("digits", require("digits")?)?)
+            .map_err(|_| OtpError::InvalidParam(String::from("digits")))?;
+        let period = parse_number("period", require("period")?)?;
+        let epoch = match get("epoch") {
+            Some(epoch) => parse_number("epoch", epoch)?,
+            None => 0,
+        };
+        validate_digits(digits)?;
+        validate_period(period)?;
+        let secret = base32_decode_verbose(secret)?;
+        Ok(TOTP::from_parts(secret, mac_digest, digits, period, epoch))
+    }
 }
 
 /// All helper methods for totp generation
 impl TOTP {
+    /// Returns the time in seconds elapsed since the epoch and the given
+    /// start time, applying the generator's [`UnderflowPolicy`].
+    ///
+    /// # Panics
+    /// This method panics if the time is before the epoch and start time,
+    /// and the policy is [`UnderflowPolicy::Error`].
+    fn elapsed(&self, time: u64, time_start: u64) -> u64 {
+        self.try_elapsed(time, time_start)
+            .expect("Time is before the TOTP start time")
+    }
+
+    /// Returns the time in seconds elapsed since the epoch and the given
+    /// start time like [`TOTP::elapsed`], without panicking.
+    ///
+    /// # Errors
+    /// Returns [`OtpError::TimeBeforeEpoch`] with the epoch plus start time
+    /// if the time is before them, and the policy is
+    /// [`UnderflowPolicy::Error`].
+    fn try_elapsed(&self, time: u64, time_start: u64) -> Result<u64, OtpError> {
+        let epoch = self.epoch.saturating_add(time_start);
+        match (time.checked_sub(epoch), self.underflow_policy) {
+            (Some(elapsed), _) => Ok(elapsed),
+            (None, UnderflowPolicy::Saturate) => Ok(0),
+            (None, UnderflowPolicy::Error) => Err(OtpError::TimeBeforeEpoch { time, epoch }),
+        }
+    }
 
     /// Returns the time in seconds until an OTP refresh is needed.
     ///
@@ -166,9 +772,82 @@ impl TOTP {
     /// method, this method takes the current time in seconds along with a
     /// specified start time in case an offset is desired. Both values must be
     /// in seconds.
+    ///
+    /// The start time is applied on top of the generator's epoch.
     pub fn time_until_refresh_with_start(&self, time: u64, time_start: u64) -> u64 {
-        let time_until = (time - time_start) % self.period;
-        if time_until == 0 { self.period } else { time_until }
+        self.period - self.elapsed(time, time_start) % self.period
+    }
+
+    /// Returns the index of the time-step the specified time is in, which
+    /// is the counter the code for that time is computed from.
+    ///
+    /// # Panics
+    /// This method panics if the time is before the epoch while the
+    /// underflow policy is [`UnderflowPolicy::Error`].
+    pub fn time_step(&self, time: u64) -> u64 {
+        self.elapsed(time, 0) / self.period
+    }
+
+    /// Returns the start and (exclusive) end unix times of the time-step the
+    /// specified time is in.
+    ///
+    /// The bounds are counted from the generator's epoch, and the specified
+    /// time always satisfies `start <= time < end`, unless it's before the
+    /// epoch with [`UnderflowPolicy::Saturate`], where the first step's
+    /// bounds are returned. The end is clamped to [`u64::MAX`].
+    ///
+    /// # Panics
+    /// This method panics if the time is before the epoch while the
+    /// underflow policy is [`UnderflowPolicy::Error`].
+    pub fn time_step_bounds(&self, time: u64) -> (u64, u64) {
+        let start = self.epoch + self.time_step(time) * self.period;
+        (start, start.saturating_add(self.period))
+    }
+
+    /// Returns the moving factor of the specified time, which is the
+    /// message the HMAC is computed over.
+    ///
+    /// This is the index of the time-step (see [`TOTP::time_step`]) as 8
+    /// big-endian bytes. It's only useful to show how a code is derived, and
+    /// has no effect on generation.
+    ///
+    /// # Panics
+    /// This method panics if the [`TOTP::time_step`] method does.
+    pub fn moving_factor_bytes(&self, time: u64) -> [u8; 8] {
+        self.time_step(time).to_be_bytes()
+    }
+
+    /// Returns the offset of the 4 bytes of the HMAC result the code for the
+    /// specified time is computed from.
+    ///
+    /// With the default [`TruncationOffset::Dynamic`] offset, this is the
+    /// low nibble of the last byte of the HMAC result, so it's between 0 and
+    /// 15. With a [`TruncationOffset::Fixed`] offset, it's that offset. Like
+    /// [`TOTP::moving_factor_bytes`], this is only useful to show how a code
+    /// is derived.
+    ///
+    /// # Panics
+    /// This method panics if the [`TOTP::time_step`] method does.
+    pub fn truncation_offset(&self, time: u64) -> usize {
+        let hash = self.prepare_mac().hash(&self.moving_factor_bytes(time));
+        resolve_truncation_offset(&hash, &self.mac_digest, self.truncation_offset)
+    }
+
+    /// Returns the number of full periods in the half-open time range
+    /// `[start, end)`.
+    ///
+    /// This is the length of the range divided by the period, rounded down,
+    /// regardless of where the time-step boundaries fall in the range.
+    ///
+    /// # Errors
+    /// Returns [`OtpError::InvalidTimeRange`] if `end` is before `start`,
+    /// and [`OtpError::InvalidPeriod`] if the period is zero.
+    pub fn periods_between(&self, start: u64, end: u64) -> Result<u64, OtpError> {
+        validate_period(self.period)?;
+        let length = end
+            .checked_sub(start)
+            .ok_or(OtpError::InvalidTimeRange { start, end })?;
+        Ok(length / self.period)
     }
 }
 
@@ -181,31 +860,571 @@ impl TOTP {
     ///
     /// # Panics
     /// This method panics if the [`TOTP::get_otp_with_custom_time_start`]
-    /// method does, which happens if the hash's secret is incorrectly given.
+    /// method does, which happens if the hash's secret is incorrectly given
+    /// or if the time is before the epoch (see [`UnderflowPolicy`]).
     pub fn get_otp(&self, time: u64) -> OTPResult {
         self.get_otp_with_custom_time_start(time, 0)
     }
 
+    /// Generates and returns the TOTP value for the specified time like
+    /// [`TOTP::get_otp`], returning an error instead of panicking on any
+    /// input.
+    ///
+    /// The non-validating constructors (such as [`TOTP::new`]) accept any
+    /// configuration, so this is the method to use when the generator's
+    /// parameters or the time come from an untrusted source.
+    ///
+    /// # Errors
+    /// Returns [`OtpError::InvalidPeriod`] if the period is zero,
+    /// [`OtpError::TimeBeforeEpoch`] if the time is before the epoch while the
+    /// underflow policy is [`UnderflowPolicy::Error`],
+    /// [`OtpError::InvalidDigits`] if the code can't have that many digits,
+    /// and [`OtpError::HashTooShort`] if the truncation offset doesn't fit
+    /// in the hash.
+    pub fn try_get_otp(&self, time: u64) -> Result<OTPResult, OtpError> {
+        validate_period(self.period)?;
+        let time_count = self.try_elapsed(time, 0)? / self.period;
+        let hash = self.prepare_mac().hash(&time_count.to_be_bytes());
+        let bytes = try_truncate(&hash, &self.mac_digest, self.truncation_offset)?;
+        let code = try_get_code(bytes, self.digits)?;
+        Ok(OTPResult::new(self.digits, code))
+    }
+
+    /// Generates and returns the TOTP value for the specified UTC calendar
+    /// date and time.
+    ///
+    /// The date is converted to unix seconds and passed to
+    /// [`TOTP::try_get_otp`]. Leap seconds are ignored, as in unix time.
+    ///
+    /// # Errors
+    /// Returns [`OtpError::InvalidDate`] if the date or time is invalid or
+    /// before the unix epoch, and the errors of [`TOTP::try_get_otp`].
+    #[cfg(feature = "calendar")]
+    pub fn get_otp_from_utc(
+        &self,
+        year: i32,
+        month: u32,
+        day: u32,
+        hour: u32,
+        minute: u32,
+        second: u32,
+    ) -> Result<OTPResult, OtpError> {
+        self.try_get_otp(utc_to_unix(year, month, day, hour, minute, second)?)
+    }
+
+    /// Generates and returns the TOTP value for the current system time.
+    ///
+    /// # Panics
+    /// This method panics if the system time is before the unix epoch, or
+    /// if the [`TOTP::get_otp`] method does.
+    pub fn get_otp_now(&self) -> OTPResult {
+        self.get_otp(current_time())
+    }
+
+    /// Generates and returns the TOTP value for the current system time,
+    /// returning an error instead of panicking like [`TOTP::try_get_otp`].
+    ///
+    /// A system clock before the epoch (such as an uninitialized clock
+    /// reading 1970 with a later epoch) gives an error instead of a code,
+    /// unless the underflow policy is [`UnderflowPolicy::Saturate`].
+    ///
+    /// # Errors
+    /// Returns [`OtpError::SystemTimeBeforeUnixEpoch`] with how far before
+    /// the unix epoch the system time is, if it is. Returns
+    /// [`OtpError::TimeBeforeEpoch`] if the system time is before the
+    /// generator's epoch, and the other errors of [`TOTP::try_get_otp`].
+    pub fn try_get_otp_now(&self) -> Result<OTPResult, OtpError> {
+        match SystemTime::now().duration_since(UNIX_EPOCH) {
+            Ok(now) => self.try_get_otp(now.as_secs()),
+            Err(e) => Err(OtpError::SystemTimeBeforeUnixEpoch(e.duration())),
+        }
+    }
+
+    /// Generates and returns the TOTP value for the specified time, given as
+    /// the duration since the unix epoch.
+    ///
+    /// The duration is truncated to whole seconds, so this is equivalent to
+    /// calling [`TOTP::get_otp`] with [`Duration::as_secs`], but keeps the
+    /// conversion in one place.
+    ///
+    /// # Panics
+    /// This method panics if the [`TOTP::get_otp`] method does.
+    pub fn get_otp_from_duration(&self, since_epoch: Duration) -> OTPResult {
+        self.get_otp(since_epoch.as_secs())
+    }
+
+    /// Generates and returns a TOTP value for the specified time where all
+    /// digits carry entropy, even with more than 8 digits.
+    ///
+    /// A standard code is the remainder of a 31-bit number, so with 10
+    /// digits its first digit is always 0, 1 or 2. This method instead takes
+    /// the last 8 digits from the standard truncation, and the digits above
+    /// those from a second truncation of the same HMAC result (the 4 bytes
+    /// after the first ones). Codes of 8 digits or less are the same as
+    /// [`TOTP::get_otp`].
+    ///
+    /// This is a non-standard extension: the codes of more than 8 digits
+    /// don't match the ones of [RFC6238] authenticator apps, so both sides
+    /// have to use this method.
+    ///
+    /// # Panics
+    /// This method panics if the generator has more than 16 digits, or if
+    /// the [`TOTP::get_otp`] method does.
+    ///
+    /// [RFC6238]: https://datatracker.ietf.org/doc/html/rfc6238
+    pub fn get_otp_extended(&self, time: u64) -> OTPResult {
+        if self.digits <= 8 {
+            return self.get_otp(time);
+        }
+        assert!(self.digits <= 16, "Extended codes can't have more than 16 digits");
+        let time_count = self.elapsed(time, 0) / self.period;
+        let hash = self.prepare_mac().hash(&time_count.to_be_bytes());
+        let (low, high) = truncate_extended(&hash, &self.mac_digest, self.truncation_offset);
+        let code = get_code(high, self.digits - 8) * 10u64.pow(8) + get_code(low, 8);
+        OTPResult::new(self.digits, code)
+    }
+
+    /// Generates and returns the TOTP value for the specified time along
+    /// with the index of its time-step.
+    ///
+    /// Both values are computed from the same time-step, so they're always
+    /// consistent, and can be used together as an idempotency key for the
+    /// action the code authorizes. The step is the same as
+    /// [`TOTP::time_step`].
+    ///
+    /// # Panics
+    /// This method panics if the [`TOTP::get_otp`] method does.
+    pub fn get_otp_with_step(&self, time: u64) -> (u64, OTPResult) {
+        let time_count = self.time_step(time);
+        (time_count, self.get_otp_for_step(&self.prepare_mac(), time_count))
+    }
+
+    /// Generates and returns the TOTP value for the specified time along
+    /// with the time in seconds until it refreshes.
+    ///
+    /// Both values are computed from the same time, so the code and the
+    /// countdown are always consistent, even right at a period boundary
+    /// (where the countdown is the full period).
+    ///
+    /// # Panics
+    /// This method panics if the [`TOTP::get_otp`] method does.
+    pub fn get_otp_and_remaining(&self, time: u64) -> (OTPResult, u64) {
+        (self.get_otp(time), self.time_until_refresh(time))
+    }
+
     /// Generates and returns the TOTP value for the specified time.
     ///
     /// The time must be specified in seconds to calculate the correct
     /// one-time password.
     ///
-    /// This method allows a custom start time to be provided.
+    /// This method allows a custom start time to be provided. The start time
+    /// is only used for this call and is applied on top of the generator's
+    /// epoch, so a generator created with [`TOTP::new_with_epoch`] counts
+    /// time-steps from `epoch + time_start`.
     ///
     /// # Panics
-    /// This method panics if the hash's secret is incorrectly given.
+    /// This method panics if the hash's secret is incorrectly given, or if
+    /// the time is before the epoch and start time while the underflow
+    /// policy is [`UnderflowPolicy::Error`].
     pub fn get_otp_with_custom_time_start(&self, time: u64, time_start: u64) -> OTPResult {
-        let time_count = (time - time_start) / self.period;
+        let time_count = self.elapsed(time, time_start) / self.period;
+        self.get_otp_for_step(&self.prepare_mac(), time_count)
+    }
 
-        let hash = hash_generic(&time_count.to_be_bytes(), &self.secret, &self.mac_digest);
-        let offset = (hash[hash.len() - 1] & 0xf) as usize;
-        let bytes: [u8; 4] = hash[offset..offset + 4]
-            .try_into()
-            .expect("Failed byte get");
+    /// Generates and returns a TOTP value of the given length in the given
+    /// radix, for the specified time.
+    ///
+    /// This is a non-standard extension used by some tokens (such as
+    /// hexadecimal ones with a radix of 16). The code is computed like
+    /// [`TOTP::get_otp`], but reduced modulo `radix` to the power of
+    /// `length` instead of a power of 10, and the returned [`OTPResult`]
+    /// displays it in that radix.
+    ///
+    /// # Panics
+    /// This method panics if the radix isn't between 2 and 36, if `radix` to
+    /// the power of `length` overflows a [`u64`], or if the
+    /// [`TOTP::get_otp`] method does.
+    pub fn get_otp_radix(&self, time: u64, radix: u32, length: u32) -> OTPResult {
+        assert!((2..=36).contains(&radix), "Radix must be between 2 and 36");
+        let time_count = self.elapsed(time, 0) / self.period;
+        let bytes = self.truncate_step(&self.prepare_mac(), time_count);
+        let code = get_code_with_radix(bytes, length, radix);
+        OTPResult::new_with_radix(length, code, radix)
+    }
+
+    /// Generates and returns the TOTP value for the specified time with each
+    /// of the [`MacDigest`] algorithms, in the order SHA1, SHA256 and SHA512.
+    ///
+    /// All other parameters (secret, digits, period, epoch and truncation
+    /// offset) are the generator's own, so each result is the same as
+    /// [`TOTP::get_otp`] on a generator with only the digest changed. This
+    /// helps find out which algorithm a provider uses when it isn't stated.
+    ///
+    /// # Panics
+    /// This method panics if the [`TOTP::get_otp`] method does, or if the
+    /// truncation offset is a fixed offset too large for a SHA1 hash.
+    pub fn get_otp_all_digests(&self, time: u64) -> Vec<(MacDigest, OTPResult)> {
+        let time_count = self.elapsed(time, 0) / self.period;
+        [MacDigest::SHA1, MacDigest::SHA256, MacDigest::SHA512]
+            .into_iter()
+            .map(|digest| {
+                let hash = PreparedMac::new(&self.secret, &digest).hash(&time_count.to_be_bytes());
+                let bytes = truncate(&hash, &digest, self.truncation_offset);
+                let code = get_code(bytes, self.digits);
+                (digest, OTPResult::new(self.digits, code))
+            })
+            .collect()
+    }
+
+    /// Generates and returns the TOTP values for each of the specified times,
+    /// in the same order.
+    ///
+    /// The HMAC is only keyed once for all times (see [`TOTP::prepared`]),
+    /// so this is faster than calling [`TOTP::get_otp`] for each time when
+    /// generating many codes.
+    ///
+    /// # Panics
+    /// This method panics if the [`TOTP::get_otp`] method does for any of
+    /// the times.
+    pub fn get_otps_at(&self, times: &[u64]) -> Vec<OTPResult> {
+        let prepared = self.prepared();
+        times.iter().map(|time| prepared.get_otp(*time)).collect()
+    }
+
+    /// Returns a generator with the HMAC already keyed with the secret.
+    ///
+    /// Keying the HMAC is a significant part of the cost of generating a
+    /// code, and [`TOTP::get_otp`] does it on each call. The returned
+    /// [`PreparedTotp`] only does it once, so it's faster when generating
+    /// many codes with the same generator, such as in a busy verification
+    /// server.
+    pub fn prepared(&self) -> PreparedTotp<'_> {
+        PreparedTotp {
+            totp: self,
+            mac: self.prepare_mac(),
+        }
+    }
 
+    /// Keys a new HMAC instance with the generator's secret and digest.
+    ///
+    /// With the `mac-cache` feature, the instance is only keyed on the first
+    /// call, and later calls return a copy of the cached one.
+    fn prepare_mac(&self) -> PreparedMac {
+        #[cfg(feature = "mac-cache")]
+        return self.mac_cache.get_or_init(&self.secret, &self.mac_digest).clone();
+        #[cfg(not(feature = "mac-cache"))]
+        PreparedMac::new(&self.secret, &self.mac_digest)
+    }
 
+    /// Generates and returns the TOTP value for the given time-step, using
+    /// the given keyed HMAC instance.
+    fn get_otp_for_step(&self, mac: &PreparedMac, time_count: u64) -> OTPResult {
+        let bytes = self.truncate_step(mac, time_count);
         let code = get_code(bytes, self.digits);
         OTPResult::new(self.digits, code)
     }
+
+    /// Hashes the given time-step and returns the 4 truncated bytes a code
+    /// is computed from.
+    fn truncate_step(&self, mac: &PreparedMac, time_count: u64) -> [u8; 4] {
+        let hash = mac.hash(&time_count.to_be_bytes());
+        truncate(&hash, &self.mac_digest, self.truncation_offset)
+    }
+}
+
+/// All otp verification methods for the [`TOTP`] struct.
+impl TOTP {
+    /// Checks whether the given code is valid for the specified time.
+    ///
+    /// Codes up to `ahead` time-steps after and `behind` time-steps before
+    /// the current one are also accepted, to allow for clock drift
+    /// between the client and the server. Both are capped at
+    /// [`crate::util::MAX_WINDOW_STEPS`].
+    ///
+    /// Full-width and Arabic-Indic digits in the code are accepted as their
+    /// ASCII equivalents, and codes are compared in constant time (see
+    /// [`OTPResult::matches`]).
+    pub fn verify(&self, code: &str, time: u64, ahead: u64, behind: u64) -> bool {
+        self.verify_with_skew(code, time, ahead, behind).is_some()
+    }
+
+    /// Checks whether the given code is valid for the current system time.
+    ///
+    /// Works like [`TOTP::verify`], and mirrors [`TOTP::get_otp_now`].
+    ///
+    /// # Panics
+    /// This method panics if the system time is before the unix epoch.
+    pub fn verify_now(&self, code: &str, ahead: u64, behind: u64) -> bool {
+        self.verify(code, current_time(), ahead, behind)
+    }
+
+    /// Checks whether the given code is valid for the specified time like
+    /// [`TOTP::verify`], returning the offset (in time-steps) of the step it
+    /// matched.
+    ///
+    /// A negative offset is a step in the past, which means the client's
+    /// clock is behind. The offsets can be aggregated in a [`SkewHistogram`]
+    /// to detect systematic clock problems.
+    pub fn verify_with_skew(&self, code: &str, time: u64, ahead: u64, behind: u64) -> Option<i64> {
+        let code = normalize_digits(code);
+        self.get_window(time, ahead, behind)
+            .find(|(_, otp)| constant_time_eq(otp.as_string().as_bytes(), code.as_bytes()))
+            .map(|(offset, _)| offset)
+    }
+
+    /// Checks a list of candidate codes against the window around the
+    /// specified time.
+    ///
+    /// Each code in the window is only generated once and all candidates
+    /// are checked against it, which is cheaper than calling
+    /// [`TOTP::verify`] for each candidate.
+    ///
+    /// Returns the index of the first matching candidate along with the
+    /// offset (in time-steps) of the step it matched, where a negative
+    /// offset is a step in the past. If a candidate matches more than one
+    /// step, the step closest to the current one is returned.
+    ///
+    /// As with [`TOTP::verify`], full-width and Arabic-Indic digits are
+    /// accepted as their ASCII equivalents.
+    pub fn verify_batch(
+        &self,
+        codes: &[&str],
+        time: u64,
+        ahead: u64,
+        behind: u64,
+    ) -> Option<(usize, i64)> {
+        let codes: Vec<String> = codes.iter().map(|code| normalize_digits(code)).collect();
+        let window: Vec<(i64, String)> = self
+            .get_window(time, ahead, behind)
+            .map(|(offset, otp)| (offset, otp.as_string()))
+            .collect();
+
+        codes.iter().enumerate().find_map(|(index, code)| {
+            window
+                .iter()
+                .find(|(_, otp)| constant_time_eq(otp.as_bytes(), code.as_bytes()))
+                .map(|(offset, _)| (index, *offset))
+        })
+    }
+
+    /// Classifies the given code by the time-step it's valid for, among the
+    /// current, previous and next time-steps of the specified time.
+    ///
+    /// This allows telling a user that their code just expired instead of
+    /// only rejecting it. If the code is valid for more than one of the
+    /// steps, the current step takes precedence.
+    ///
+    /// # Panics
+    /// This method panics if the time is before the epoch while the
+    /// underflow policy is [`UnderflowPolicy::Error`].
+    pub fn classify(&self, code: &str, time: u64) -> CodeValidity {
+        match self.verify_batch(&[code], time, 1, 1) {
+            Some((_, 0)) => CodeValidity::Current,
+            Some((_, -1)) => CodeValidity::Previous,
+            Some((_, 1)) => CodeValidity::Next,
+            _ => CodeValidity::Invalid,
+        }
+    }
+
+    /// Returns the set of codes accepted around the specified time.
+    ///
+    /// The set holds the zero-padded codes (as returned by
+    /// [`OTPResult::as_string`]) of the steps from `behind` time-steps before
+    /// to `ahead` time-steps after the current one. A server can compute it
+    /// once per request and check a submitted code with a single lookup.
+    pub fn acceptable_codes(&self, time: u64, ahead: u64, behind: u64) -> HashSet<String> {
+        self.get_window(time, ahead, behind)
+            .map(|(_, otp)| otp.as_string())
+            .collect()
+    }
+
+    /// Generates the codes from `behind` time-steps before to `ahead`
+    /// time-steps after the one of the specified time.
+    ///
+    /// Each code is returned with its offset from the current step, ordered
+    /// by distance to the current step. Both `ahead` and `behind` are capped
+    /// at [`crate::util::MAX_WINDOW_STEPS`], and the codes are generated lazily.
+    fn get_window(
+        &self,
+        time: u64,
+        ahead: u64,
+        behind: u64,
+    ) -> impl Iterator<Item = (i64, OTPResult)> + '_ {
+        let current = self.elapsed(time, 0) / self.period;
+        let mac = self.prepare_mac();
+        window_steps(current, ahead, behind)
+            .map(move |(offset, step)| (offset, self.get_otp_for_step(&mac, step)))
+    }
+}
+
+/// All configuration checks for the [`TOTP`] struct.
+impl TOTP {
+    /// Checks whether any two adjacent codes are equal among `steps`
+    /// consecutive codes, starting at the time-step of `around_time`.
+    ///
+    /// This isn't a cryptographic test, but a lightweight sanity check that
+    /// can catch a broken secret or configuration (such as a very low digit
+    /// count) at enrollment. Returns false if fewer than 2 steps are given.
+    /// `steps` is capped at [`MAX_WINDOW_STEPS`], as every step has to be
+    /// hashed.
+    pub fn has_degenerate_output(&self, around_time: u64, steps: u64) -> bool {
+        let start = self.elapsed(around_time, 0) / self.period;
+        let end = start.saturating_add(steps.min(MAX_WINDOW_STEPS));
+        let mac = self.prepare_mac();
+        let mut previous = None;
+        (start..end).any(|step| {
+            let code = self.get_otp_for_step(&mac, step);
+            previous.replace(code) == Some(code)
+        })
+    }
+
+    /// Returns advisory warnings about unusual parts of the configuration,
+    /// in the order: period, digits, digest.
+    ///
+    /// The warnings are meant to be shown when a user chooses a
+    /// configuration, and an empty list means the configuration is the
+    /// common one. See [`ConfigWarning`] for the checks.
+    pub fn lint(&self) -> Vec<ConfigWarning> {
+        let mut warnings = Vec::new();
+        if self.period < 15 {
+            warnings.push(ConfigWarning::ShortPeriod(self.period));
+        }
+        if self.digits < 6 {
+            warnings.push(ConfigWarning::FewDigits(self.digits));
+        } else if self.digits > 8 {
+            warnings.push(ConfigWarning::ManyDigits(self.digits));
+        }
+        if self.mac_digest != MacDigest::SHA1 {
+            warnings.push(ConfigWarning::UncommonDigest(self.mac_digest));
+        }
+        warnings
+    }
+}
+
+/// A [`TOTP`] generator with its HMAC already keyed with the secret.
+///
+/// Created with [`TOTP::prepared`], and generates the same codes as the
+/// [`TOTP`] it was created from, without keying the HMAC again for each
+/// code.
+#[derive(Debug, Clone)]
+pub struct PreparedTotp<'a> {
+    /// The generator the HMAC was keyed from.
+    totp: &'a TOTP,
+
+    /// The HMAC instance keyed with the generator's secret.
+    mac: PreparedMac,
+}
+
+/// All otp generation methods for the [`PreparedTotp`] struct.
+impl PreparedTotp<'_> {
+    /// Generates and returns the TOTP value for the specified time.
+    ///
+    /// Works like [`TOTP::get_otp`].
+    ///
+    /// # Panics
+    /// This method panics if the time is before the epoch while the
+    /// underflow policy is [`UnderflowPolicy::Error`].
+    pub fn get_otp(&self, time: u64) -> OTPResult {
+        let time_count = self.totp.elapsed(time, 0) / self.totp.period;
+        self.totp.get_otp_for_step(&self.mac, time_count)
+    }
+}
+
+/// All comparison methods for the [`TOTP`] struct.
+impl TOTP {
+    /// Checks whether this generator and another one produce the same codes
+    /// at each of the given sample times.
+    ///
+    /// This is useful as a sanity check when migrating a secret to a new
+    /// configuration, as any mismatch in the secret, digest, digits or
+    /// period will show up as a differing code.
+    pub fn produces_same_codes(&self, other: &TOTP, sample_times: &[u64]) -> bool {
+        self.first_divergent_time(other, sample_times).is_none()
+    }
+
+    /// Returns the first of the given sample times at which this generator
+    /// and another one produce different codes, if any.
+    ///
+    /// Works like [`TOTP::produces_same_codes`], but is more helpful when
+    /// debugging a mismatch.
+    pub fn first_divergent_time(&self, other: &TOTP, sample_times: &[u64]) -> Option<u64> {
+        sample_times
+            .iter()
+            .copied()
+            .find(|time| self.get_otp(*time) != other.get_otp(*time))
+    }
+}
+
+/// A histogram of the clock skew offsets returned by
+/// [`TOTP::verify_with_skew`].
+///
+/// A server can record the offset of each successful verification across
+/// its users, and use the [`SkewHistogram::summary`] to detect systematic
+/// clock problems (such as most clients being one step behind).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SkewHistogram {
+    counts: BTreeMap<i64, u64>,
+}
+
+/// A summary of the offsets recorded in a [`SkewHistogram`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SkewSummary {
+    /// The mean offset, in time-steps, or 0 if nothing was recorded.
+    pub mean: f64,
+
+    /// The largest absolute offset, in time-steps, or 0 if nothing was
+    /// recorded.
+    pub max_abs: u64,
+
+    /// The number of times each offset was recorded, ordered by offset.
+    pub counts_per_offset: BTreeMap<i64, u64>,
+}
+
+/// All methods for the [`SkewHistogram`] struct.
+impl SkewHistogram {
+    /// Creates a new empty histogram.
+    pub fn new() -> Self {
+        SkewHistogram::default()
+    }
+
+    /// Records an offset, in time-steps.
+    pub fn record(&mut self, offset: i64) {
+        *self.counts.entry(offset).or_insert(0) += 1;
+    }
+
+    /// Returns the number of recorded offsets.
+    pub fn total(&self) -> u64 {
+        self.counts.values().sum()
+    }
+
+    /// Returns a summary of the recorded offsets.
+    pub fn summary(&self) -> SkewSummary {
+        let total = self.total();
+        let sum: f64 = self
+            .counts
+            .iter()
+            .map(|(offset, count)| *offset as f64 * *count as f64)
+            .sum();
+        SkewSummary {
+            mean: if total == 0 { 0.0 } else { sum / total as f64 },
+            max_abs: self
+                .counts
+                .keys()
+                .map(|offset| offset.unsigned_abs())
+                .max()
+                .unwrap_or(0),
+            counts_per_offset: self.counts.clone(),
+        }
+    }
+}
+
+/// Returns the current system time in seconds since the unix epoch.
+///
+/// # Panics
+/// This method panics if the system time is before the unix epoch.
+fn current_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Error getting time")
+        .as_secs()
 }
@@ -1,10 +1,19 @@
-use base32::Alphabet;
 use hmac::{Hmac, Mac};
+use hmac::digest::KeyInit;
 use sha1::Sha1;
 use sha2::{Sha256, Sha512};
 use std::collections::HashMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+#[cfg(feature = "mac-cache")]
+use std::sync::OnceLock;
+use std::time::Duration;
 use url::Url;
 
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroizing;
+
 use crate::hotp::HOTP;
 use crate::totp::TOTP;
 
@@ -20,40 +29,496 @@ use crate::totp::TOTP;
 ///
 /// [RFC6238]: https://datatracker.ietf.org/doc/html/rfc6238
 #[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum MacDigest {
     SHA1,
     SHA256,
     SHA512,
 }
 
+/// Size getters for the [`MacDigest`] enum.
+impl MacDigest {
+    /// Returns the length in bytes of the HMAC output for the digest.
+    ///
+    /// This is 20 for SHA1, 32 for SHA256 and 64 for SHA512.
+    pub fn output_len(&self) -> usize {
+        match *self {
+            MacDigest::SHA1 => 20,
+            MacDigest::SHA256 => 32,
+            MacDigest::SHA512 => 64,
+        }
+    }
+
+    /// Returns the internal block size in bytes of the digest.
+    ///
+    /// This is 64 for SHA1 and SHA256, and 128 for SHA512.
+    ///
+    /// As specified by [RFC2104], secrets longer than the block size are
+    /// hashed with the digest before being used as the HMAC key.
+    ///
+    /// [RFC2104]: https://datatracker.ietf.org/doc/html/rfc2104
+    pub fn block_size(&self) -> usize {
+        match *self {
+            MacDigest::SHA1 | MacDigest::SHA256 => 64,
+            MacDigest::SHA512 => 128,
+        }
+    }
+}
+
+/// The maximum number of digits a generated code can have.
+///
+/// The dynamically truncated value is 31 bits long (at most 2147483647), so
+/// codes beyond 9 digits don't get any more entropy: a 10-digit code always
+/// has a leading digit of 0, 1 or 2. Even at 9 digits the leading digit is
+/// slightly biased, so codes longer than 8 digits should be avoided unless
+/// required for compatibility.
+pub const MAX_DIGITS: u32 = 10;
+
+/// The maximum number of steps (or counters) checked on each side of the
+/// current one when verifying a code.
+///
+/// Larger `ahead`/`behind` values given to the verification methods are
+/// capped at this, as every step in the window has to be hashed.
+pub const MAX_WINDOW_STEPS: u64 = 1000;
+
+/// Different error types of the HOTP/TOTP configuration and generation.
+///
+/// Represents each error that could occur while creating a generator with
+/// one of the validating constructors (such as [`TOTP::try_new`]) or while
+/// generating a code. The returned error may have an associated value with
+/// more information. New variants may be added in minor releases, so
+/// matches need a wildcard arm.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum OtpError {
+    InvalidDigits(u32),
+    InvalidPeriod(u64),
+    InvalidBase32 { position: usize, character: char },
+    InvalidBase32Length(usize),
+    NonZeroBase32TrailingBits { position: usize, character: char },
+    BufferTooSmall { needed: usize, available: usize },
+    InvalidTruncationOffset(usize),
+    TimeBeforeEpoch { time: u64, epoch: u64 },
+    SystemTimeBeforeUnixEpoch(Duration),
+    HashTooShort(usize),
+    SecretTooShort { length: usize, minimum: usize },
+    MissingParam(String),
+    InvalidParam(String),
+    InvalidDate,
+    DisallowedAlgorithm(MacDigest),
+    InvalidTimeRange { start: u64, end: u64 },
+    MalformedBackup(String),
+    InvalidUri(ParseError),
+    TooManyBackupCodes { count: usize, possible: u64 },
+}
+
+/// Returns the steps from `behind` steps before to `ahead` steps after
+/// `current`, each with its offset from `current`, ordered by distance to
+/// `current` (the earlier step first on a tie).
+///
+/// Both `ahead` and `behind` are capped at [`MAX_WINDOW_STEPS`], and steps
+/// outside of the range of a [`u64`] are skipped. The steps are generated
+/// lazily.
+pub(crate) fn window_steps(
+    current: u64,
+    ahead: u64,
+    behind: u64,
+) -> impl Iterator<Item = (i64, u64)> {
+    let ahead = ahead.min(MAX_WINDOW_STEPS);
+    let behind = behind.min(MAX_WINDOW_STEPS);
+    (0..=ahead.max(behind)).flat_map(move |distance| {
+        let before = match distance {
+            0 => None,
+            d if d <= behind => current.checked_sub(d).map(|step| (-(d as i64), step)),
+            _ => None,
+        };
+        let after = match distance {
+            d if d <= ahead => current.checked_add(d).map(|step| (d as i64, step)),
+            _ => None,
+        };
+        before.into_iter().chain(after)
+    })
+}
+
+/// Checks that the digit count is between 1 and [`MAX_DIGITS`].
+pub(crate) fn validate_digits(digits: u32) -> Result<(), OtpError> {
+    if digits == 0 || digits > MAX_DIGITS {
+        return Err(OtpError::InvalidDigits(digits));
+    }
+    Ok(())
+}
+
+/// Checks that the period is a non-zero amount of seconds.
+pub(crate) fn validate_period(period: u64) -> Result<(), OtpError> {
+    if period == 0 {
+        return Err(OtpError::InvalidPeriod(period));
+    }
+    Ok(())
+}
+
+/// Converts a UTC calendar date and time to unix seconds.
+///
+/// Uses the proleptic Gregorian calendar, and ignores leap seconds like unix
+/// time does, so a second of 60 is invalid.
+///
+/// # Errors
+/// Returns [`OtpError::InvalidDate`] if any component is out of range (such
+/// as February 30th), or if the date is before the unix epoch.
+#[cfg(feature = "calendar")]
+pub(crate) fn utc_to_unix(
+    year: i32,
+    month: u32,
+    day: u32,
+    hour: u32,
+    minute: u32,
+    second: u32,
+) -> Result<u64, OtpError> {
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let days_in_month = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if leap => 29,
+        2 => 28,
+        _ => return Err(OtpError::InvalidDate),
+    };
+    if day == 0 || day > days_in_month || hour > 23 || minute > 59 || second > 59 {
+        return Err(OtpError::InvalidDate);
+    }
+
+    // Days since the epoch, counting years from March so the leap day is
+    // the last day of the year.
+    let year = year as i64 - if month <= 2 { 1 } else { 0 };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month as i64 + 9) % 12) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146097 + day_of_era - 719468;
+
+    let seconds = days * 86400 + hour as i64 * 3600 + minute as i64 * 60 + second as i64;
+    u64::try_from(seconds).map_err(|_| OtpError::InvalidDate)
+}
+
+/// Checks that the secret is at least as long as the digest's output, as
+/// recommended by [RFC6238] for the HMAC key.
+///
+/// [RFC6238]: https://datatracker.ietf.org/doc/html/rfc6238
+pub(crate) fn validate_secret_length(secret: &[u8], digest: &MacDigest) -> Result<(), OtpError> {
+    if secret.len() < digest.output_len() {
+        return Err(OtpError::SecretTooShort {
+            length: secret.len(),
+            minimum: digest.output_len(),
+        });
+    }
+    Ok(())
+}
+
 /// A generic method to convert the [H/T]OTP byte-array into the
 /// requested decimal-based code.
 ///
 /// Needs the bytes to convert and the amount of digits the code should be.
-pub(crate) fn get_code(bytes: [u8; 4], digits: u32) -> u32 {
-    let code = (((bytes[0] & 0x7f) as u32) << 24)
-        | ((bytes[1] as u32) << 16)
-        | ((bytes[2] as u32) << 8)
-        | bytes[3] as u32;
-    code % (10_u32.pow(digits))
+///
+/// The bytes are read as a big-endian integer with the most significant
+/// bit masked off (`& 0x7fffffff`), as required by [RFC4226], so the value
+/// is the same whether it would be interpreted as signed or unsigned.
+///
+/// [RFC4226]: https://datatracker.ietf.org/doc/html/rfc4226
+///
+/// The code is computed with [`u64`] arithmetic so up to [`MAX_DIGITS`]
+/// digits can be requested.
+pub(crate) fn get_code(bytes: [u8; 4], digits: u32) -> u64 {
+    get_code_with_radix(bytes, digits, 10)
+}
+
+/// A generic method to convert the [H/T]OTP byte-array into a code of the
+/// requested length in the given radix.
+///
+/// Works like [`get_code`], which is the decimal case.
+///
+/// # Panics
+/// This method panics if `radix` to the power of `length` overflows a
+/// [`u64`].
+pub(crate) fn get_code_with_radix(bytes: [u8; 4], length: u32, radix: u32) -> u64 {
+    let code = (u32::from_be_bytes(bytes) & 0x7fff_ffff) as u64;
+    let modulus = (radix as u64)
+        .checked_pow(length)
+        .expect("Code length is too large for the radix");
+    code % modulus
+}
+
+/// Converts the [H/T]OTP byte-array into the requested decimal-based code
+/// like [`get_code`], without panicking.
+///
+/// # Errors
+/// Returns [`OtpError::InvalidDigits`] if 10 to the power of `digits`
+/// overflows a [`u64`].
+pub(crate) fn try_get_code(bytes: [u8; 4], digits: u32) -> Result<u64, OtpError> {
+    let code = (u32::from_be_bytes(bytes) & 0x7fff_ffff) as u64;
+    let modulus = 10u64
+        .checked_pow(digits)
+        .ok_or(OtpError::InvalidDigits(digits))?;
+    Ok(code % modulus)
 }
 
-/// A method to hash a message with a given secret and digest.
+/// The offset of the 4 bytes of the HMAC result a code is computed from.
 ///
-/// The only time [`MacDigest`] is not [`MacDigest::SHA1`] is when the
-/// TOTP instance's mac_digest is set otherwise.
+/// [RFC4226] mandates [`TruncationOffset::Dynamic`], which is the default
+/// and what authenticator applications use. [`TruncationOffset::Fixed`] is
+/// only meant for interoperability with legacy tokens that don't follow the
+/// RFC.
 ///
-/// Calls the underlying [`hash_internal`] function with the correctly
-/// HMAC-mapped algorithm.
-pub(crate) fn hash_generic(msg: &[u8], secret: &[u8], digest: &MacDigest) -> Vec<u8> {
-    match *digest {
-        MacDigest::SHA1 => hash_internal::<Hmac<Sha1>>(msg, secret),
-        MacDigest::SHA256 => hash_internal::<Hmac<Sha256>>(msg, secret),
-        MacDigest::SHA512 => hash_internal::<Hmac<Sha512>>(msg, secret),
+/// [RFC4226]: https://datatracker.ietf.org/doc/html/rfc4226
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, Default)]
+pub enum TruncationOffset {
+    /// Take the offset from the low nibble of the last byte of the HMAC
+    /// result, as described in section 5.3 of [RFC4226].
+    ///
+    /// [RFC4226]: https://datatracker.ietf.org/doc/html/rfc4226
+    #[default]
+    Dynamic,
+
+    /// Always use the given offset.
+    Fixed(usize),
+}
+
+/// Checks that a truncation offset leaves room for 4 bytes in the output
+/// of the given digest.
+pub(crate) fn validate_truncation_offset(
+    offset: TruncationOffset,
+    digest: &MacDigest,
+) -> Result<(), OtpError> {
+    match offset {
+        TruncationOffset::Fixed(x) if x > digest.output_len() - 4 => {
+            Err(OtpError::InvalidTruncationOffset(x))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Truncates an HMAC result into the 4 bytes used to get the code, as
+/// described in section 5.3 of [RFC4226].
+///
+/// With a [`TruncationOffset::Dynamic`] offset, the offset is taken from the
+/// low nibble of the last byte of the digest's output, so it's at most 15
+/// and the 4 selected bytes always fit in the shortest (SHA1) output.
+/// [`TruncationOffset::Fixed`] offsets are checked with
+/// [`validate_truncation_offset`] when they're set.
+///
+/// [RFC4226]: https://datatracker.ietf.org/doc/html/rfc4226
+pub(crate) fn truncate(hash: &[u8], digest: &MacDigest, offset: TruncationOffset) -> [u8; 4] {
+    try_truncate(hash, digest, offset).expect("Failed byte get")
+}
+
+/// Returns the offset of the 4 bytes of an HMAC result a code is computed
+/// from, which is the low nibble of its last byte with a
+/// [`TruncationOffset::Dynamic`] offset.
+pub(crate) fn resolve_truncation_offset(
+    hash: &[u8],
+    digest: &MacDigest,
+    offset: TruncationOffset,
+) -> usize {
+    match offset {
+        TruncationOffset::Dynamic => (hash[digest.output_len() - 1] & 0xf) as usize,
+        TruncationOffset::Fixed(x) => x,
+    }
+}
+
+/// Truncates an HMAC result into two non-overlapping sets of 4 bytes, for
+/// codes of more than 8 digits.
+///
+/// The first set is the one returned by [`truncate`], and the second one
+/// starts 4 bytes after it, wrapping around to the start of the hash when
+/// it doesn't fit. The hash is at least 20 bytes long, so the two sets never
+/// overlap.
+pub(crate) fn truncate_extended(
+    hash: &[u8],
+    digest: &MacDigest,
+    offset: TruncationOffset,
+) -> ([u8; 4], [u8; 4]) {
+    let first = truncate(hash, digest, offset);
+    let first_offset = resolve_truncation_offset(hash, digest, offset);
+    let second_offset = (first_offset + 4) % (digest.output_len() - 3);
+    let second = truncate(hash, digest, TruncationOffset::Fixed(second_offset));
+    (first, second)
+}
+
+/// Truncates an HMAC result like [`truncate`], without panicking.
+///
+/// # Errors
+/// Returns [`OtpError::HashTooShort`] with the length of the hash if it's
+/// shorter than the digest's output, or too short for the 4 bytes at the
+/// offset.
+pub(crate) fn try_truncate(
+    hash: &[u8],
+    digest: &MacDigest,
+    offset: TruncationOffset,
+) -> Result<[u8; 4], OtpError> {
+    let too_short = OtpError::HashTooShort(hash.len());
+    let offset = match offset {
+        TruncationOffset::Dynamic => match hash.get(digest.output_len() - 1) {
+            Some(byte) => (byte & 0xf) as usize,
+            None => return Err(too_short),
+        },
+        TruncationOffset::Fixed(x) => x,
+    };
+    match hash.get(offset..) {
+        Some(&[a, b, c, d, ..]) => Ok([a, b, c, d]),
+        _ => Err(too_short),
     }
 }
 
-/// A generic method to HMAC a message using the given type.
+/// The buffer holding a generator's secret.
+///
+/// With the `zeroize` feature, this is a [`Zeroizing`] buffer, which wipes
+/// the secret from memory when dropped.
+#[cfg(feature = "zeroize")]
+type SecretBuffer = Zeroizing<Vec<u8>>;
+#[cfg(not(feature = "zeroize"))]
+type SecretBuffer = Vec<u8>;
+
+/// The secret of a [`HOTP`] or [`TOTP`] generator.
+pub(crate) struct SecretBytes(SecretBuffer);
+
+impl From<Vec<u8>> for SecretBytes {
+    fn from(secret: Vec<u8>) -> Self {
+        SecretBytes(SecretBuffer::from(secret))
+    }
+}
+
+impl Deref for SecretBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+/// A Clone implementation for the [`SecretBytes`] struct
+///
+/// The secret is copied straight into a buffer of its exact length, so the
+/// buffer is never reallocated, and with the `zeroize` feature, no copy of
+/// the secret is left outside of a [`Zeroizing`] buffer.
+impl Clone for SecretBytes {
+    fn clone(&self) -> Self {
+        let mut buffer = SecretBuffer::from(Vec::with_capacity(self.len()));
+        buffer.extend_from_slice(self);
+        SecretBytes(buffer)
+    }
+}
+
+/// A Hash implementation for the [`SecretBytes`] struct
+///
+/// Hashes the secret the same way as a [`Vec`] of its bytes.
+impl Hash for SecretBytes {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self[..].hash(state)
+    }
+}
+
+/// A Debug implementation for the [`SecretBytes`] struct
+///
+/// Shows the secret the same way as a [`Vec`] of its bytes.
+impl fmt::Debug for SecretBytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self[..], f)
+    }
+}
+
+/// An HMAC instance already keyed with a secret.
+///
+/// Keying the HMAC (which derives the inner and outer padded keys from the
+/// secret) only happens once on creation, and each message is then hashed
+/// on a cheap copy of the keyed state.
+///
+/// The only time the digest is not [`MacDigest::SHA1`] is when the TOTP
+/// instance's mac_digest is set otherwise.
+#[derive(Clone)]
+pub(crate) enum PreparedMac {
+    SHA1(Hmac<Sha1>),
+    SHA256(Hmac<Sha256>),
+    SHA512(Hmac<Sha512>),
+}
+
+impl PreparedMac {
+    /// Keys a new HMAC instance of the given digest with the secret.
+    ///
+    /// The secret is passed to the HMAC implementation as-is, which
+    /// pre-hashes secrets longer than the digest's block size as required
+    /// by RFC2104.
+    pub(crate) fn new(secret: &[u8], digest: &MacDigest) -> Self {
+        match *digest {
+            MacDigest::SHA1 => PreparedMac::SHA1(new_mac_internal(secret)),
+            MacDigest::SHA256 => PreparedMac::SHA256(new_mac_internal(secret)),
+            MacDigest::SHA512 => PreparedMac::SHA512(new_mac_internal(secret)),
+        }
+    }
+
+    /// Hashes a message with the keyed HMAC instance.
+    pub(crate) fn hash(&self, msg: &[u8]) -> Vec<u8> {
+        match self {
+            PreparedMac::SHA1(mac) => hash_internal(mac, msg),
+            PreparedMac::SHA256(mac) => hash_internal(mac, msg),
+            PreparedMac::SHA512(mac) => hash_internal(mac, msg),
+        }
+    }
+}
+
+/// A lazily keyed [`PreparedMac`], cached in a [`TOTP`] with the
+/// `mac-cache` feature.
+///
+/// The keyed state is boxed to keep the size of a [`TOTP`] small. The cache
+/// is derived from the generator's secret and digest, so it isn't
+/// part of its identity: it's ignored when hashing, and a clone starts with
+/// an empty cache, so no keyed state is copied.
+///
+/// The keyed state is as sensitive as the secret, but isn't wiped by the
+/// `zeroize` feature, as the HMAC types don't support zeroizing (only the
+/// secret buffer is). Don't combine the `mac-cache` and `zeroize` features
+/// if the keyed state must not outlive the generator in memory.
+#[cfg(feature = "mac-cache")]
+#[derive(Debug, Default)]
+pub(crate) struct MacCache(OnceLock<Box<PreparedMac>>);
+
+#[cfg(feature = "mac-cache")]
+impl MacCache {
+    /// Returns the cached HMAC instance, keying it first if needed.
+    pub(crate) fn get_or_init(&self, secret: &[u8], digest: &MacDigest) -> &PreparedMac {
+        self.0.get_or_init(|| Box::new(PreparedMac::new(secret, digest)))
+    }
+}
+
+/// A Clone implementation for the [`MacCache`] struct
+///
+/// Returns an empty cache.
+#[cfg(feature = "mac-cache")]
+impl Clone for MacCache {
+    fn clone(&self) -> Self {
+        MacCache::default()
+    }
+}
+
+/// A Hash implementation for the [`MacCache`] struct
+///
+/// Doesn't hash anything, as the cache isn't part of a generator's identity.
+#[cfg(feature = "mac-cache")]
+impl Hash for MacCache {
+    fn hash<H: Hasher>(&self, _state: &mut H) {}
+}
+
+/// A Debug implementation for the [`PreparedMac`] enum
+///
+/// Only shows the digest, to keep any key material out of logs.
+impl fmt::Debug for PreparedMac {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            PreparedMac::SHA1(_) => "SHA1",
+            PreparedMac::SHA256(_) => "SHA256",
+            PreparedMac::SHA512(_) => "SHA512",
+        };
+        write!(f, "PreparedMac({})", name)
+    }
+}
+
+/// A generic method to key an HMAC instance of the given type.
 ///
 /// This is mainly a private method made for added convenience and code
 /// readability to reduce the duplicate code with different
@@ -62,15 +527,187 @@ pub(crate) fn hash_generic(msg: &[u8], secret: &[u8], digest: &MacDigest) -> Vec
 /// # Panics
 /// The method will panic if the provided secret is invalid and a hash
 /// cannot be generated.
-fn hash_internal<D: Mac>(msg: &[u8], secret: &[u8]) -> Vec<u8> {
-    let mut hmac = <D>::new_from_slice(secret).expect("Failed to initialize HMAC");
+fn new_mac_internal<D: Mac + KeyInit>(secret: &[u8]) -> D {
+    <D as KeyInit>::new_from_slice(secret).expect("Failed to initialize HMAC")
+}
+
+/// A generic method to HMAC a message using a copy of the given keyed
+/// instance.
+fn hash_internal<D: Mac + Clone>(mac: &D, msg: &[u8]) -> Vec<u8> {
+    let mut hmac = mac.clone();
     hmac.update(msg);
     hmac.finalize().into_bytes()[..].into()
 }
 
+/// Encodes bytes into an unpadded base32 string according to RFC4648.
+pub(crate) fn base32_encode(data: &[u8]) -> String {
+    base32::encode(base32::Alphabet::RFC4648 { padding: false }, data)
+}
+
+/// The marker of bytes outside of the base32 alphabet in
+/// [`BASE32_DECODE_TABLE`].
+const BASE32_INVALID: u8 = 0xff;
+
+/// The 5-bit value of each byte in the base32 alphabet (accepting lowercase
+/// letters), or [`BASE32_INVALID`] for any other byte.
+const BASE32_DECODE_TABLE: [u8; 256] = {
+    let mut table = [BASE32_INVALID; 256];
+    let mut i = 0;
+    while i < 26 {
+        table[b'A' as usize + i] = i as u8;
+        table[b'a' as usize + i] = i as u8;
+        i += 1;
+    }
+    let mut i = 0;
+    while i < 6 {
+        table[b'2' as usize + i] = 26 + i as u8;
+        i += 1;
+    }
+    table
+};
+
 /// Decodes a base32 string according to RFC4648.
 pub(crate) fn base32_decode(data: &str) -> Option<Vec<u8>> {
-    base32::decode(Alphabet::RFC4648 { padding: false }, data)
+    base32_decode_verbose(data).ok()
+}
+
+/// Decodes a base32 string according to RFC4648, reporting where the
+/// string is invalid on failure.
+///
+/// Lowercase letters are accepted and trailing `=` padding is ignored.
+///
+/// Decoding is strict: when the string's bit length isn't a multiple of 8,
+/// the leftover bits of its last character must be zero, as required by
+/// RFC4648. Other decoders would silently drop those bits, so a secret that
+/// doesn't follow this most likely has a typo in it.
+///
+/// This method is safe and shouldn't panic.
+///
+/// # Errors
+/// Returns [`OtpError::InvalidBase32`] with the position (in characters)
+/// and value of the first character that isn't part of the base32 alphabet.
+///
+/// Returns [`OtpError::InvalidBase32Length`] with the unpadded length (in
+/// characters) if it can't be the length of an encoded byte string, which
+/// is the case for lengths of 1, 3 or 6 more than a multiple of 8.
+///
+/// Returns [`OtpError::NonZeroBase32TrailingBits`] with the position and
+/// value of the last character if its leftover bits aren't zero.
+pub fn base32_decode_verbose(data: &str) -> Result<Vec<u8>, OtpError> {
+    let unpadded = data.trim_end_matches('=').as_bytes();
+    let mut decoded = Vec::with_capacity(unpadded.len() * 5 / 8);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for (index, byte) in unpadded.iter().enumerate() {
+        let value = BASE32_DECODE_TABLE[*byte as usize];
+        if value == BASE32_INVALID {
+            // All previous bytes are valid ASCII characters, so the byte
+            // index is also the character position.
+            let character = data[index..].chars().next().expect("Failed character get");
+            return Err(OtpError::InvalidBase32 {
+                position: index,
+                character,
+            });
+        }
+        buffer = (buffer << 5) | value as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    if bits >= 5 {
+        return Err(OtpError::InvalidBase32Length(unpadded.len()));
+    }
+    if buffer != 0 {
+        return Err(OtpError::NonZeroBase32TrailingBits {
+            position: unpadded.len() - 1,
+            character: unpadded[unpadded.len() - 1] as char,
+        });
+    }
+    Ok(decoded)
+}
+
+/// A secret given as the UTF-8 bytes of a string, such as the
+/// `"12345678901234567890"` secret of the RFC test vectors.
+///
+/// This is not the format authenticator apps display secrets in, which is
+/// base32 (see [`Base32Secret`]).
+#[derive(Copy, Clone)]
+pub struct Utf8Secret<'a>(pub &'a str);
+
+/// A secret given as a base32-encoded string, the format otpauth URIs and
+/// authenticator apps use.
+#[derive(Copy, Clone)]
+pub struct Base32Secret<'a>(pub &'a str);
+
+/// A secret given as its raw bytes.
+#[derive(Copy, Clone)]
+pub struct RawSecret<'a>(pub &'a [u8]);
+
+/// A secret in one of the supported encodings, so the encoding is explicit
+/// where a generator is created (see [`TOTP::from_secret`]).
+///
+/// Created from a [`Utf8Secret`], [`Base32Secret`] or [`RawSecret`].
+#[derive(Copy, Clone)]
+pub enum SecretInput<'a> {
+    Utf8(&'a str),
+    Base32(&'a str),
+    Raw(&'a [u8]),
+}
+
+impl<'a> From<Utf8Secret<'a>> for SecretInput<'a> {
+    fn from(secret: Utf8Secret<'a>) -> Self {
+        SecretInput::Utf8(secret.0)
+    }
+}
+
+impl<'a> From<Base32Secret<'a>> for SecretInput<'a> {
+    fn from(secret: Base32Secret<'a>) -> Self {
+        SecretInput::Base32(secret.0)
+    }
+}
+
+impl<'a> From<RawSecret<'a>> for SecretInput<'a> {
+    fn from(secret: RawSecret<'a>) -> Self {
+        SecretInput::Raw(secret.0)
+    }
+}
+
+impl SecretInput<'_> {
+    /// Decodes the secret into its bytes.
+    ///
+    /// # Errors
+    /// Returns the error of [`base32_decode_verbose`] if a base32 secret is
+    /// not correctly encoded.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, OtpError> {
+        match self {
+            SecretInput::Utf8(secret) => Ok(secret.as_bytes().to_vec()),
+            SecretInput::Base32(secret) => base32_decode_verbose(secret),
+            SecretInput::Raw(secret) => Ok(secret.to_vec()),
+        }
+    }
+}
+
+/// Maps an algorithm name, as used in otpauth URIs and backups, to its
+/// [`MacDigest`].
+pub(crate) fn parse_algorithm(name: &str) -> Option<MacDigest> {
+    match name {
+        "SHA1" => Some(MacDigest::SHA1),
+        "SHA256" => Some(MacDigest::SHA256),
+        "SHA512" => Some(MacDigest::SHA512),
+        _ => None,
+    }
+}
+
+/// Maps a [`MacDigest`] to its algorithm name, as used in otpauth URIs.
+pub(crate) fn algorithm_name(digest: &MacDigest) -> &'static str {
+    match digest {
+        MacDigest::SHA1 => "SHA1",
+        MacDigest::SHA256 => "SHA256",
+        MacDigest::SHA512 => "SHA512",
+    }
 }
 
 /// Result of an otpauth URI parsing.
@@ -90,8 +727,10 @@ pub enum ParseResult {
 ///
 /// Represents each error that could occur while parsing the otpauth URI
 /// in an enum. The returned error may have an associated message or
-/// [url::ParseError] with more information
-#[derive(Debug)]
+/// [url::ParseError] with more information. New variants may be added in
+/// minor releases, so matches need a wildcard arm.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum ParseError {
     UriParseError(url::ParseError),
     WrongScheme(String),
@@ -104,6 +743,45 @@ pub enum ParseError {
     MissingCounter,
     WrongCounter(String),
     InvalidPeriod(String),
+    UnexpectedOtpType(String),
+    MalformedUri { param: String, value: String },
+}
+
+/// Builds an otpauth URI of the given type (`hotp` or `totp`).
+///
+/// The label is prefixed with the issuer (if any), as recommended by the
+/// Key URI Format, and the parameters are added after the secret and the
+/// issuer, in the order given. Both the label and the parameters are
+/// percent-encoded.
+pub(crate) fn build_otpauth_uri(
+    otp_type: &str,
+    label: &str,
+    issuer: Option<&str>,
+    secret: &[u8],
+    params: &[(&str, &str)],
+) -> String {
+    let mut uri = Url::parse("otpauth://").expect("Failed to create otpauth URI");
+    uri.set_host(Some(otp_type))
+        .expect("Failed to set otpauth URI type");
+    let label = match issuer {
+        Some(issuer) => format!("{}:{}", issuer, label),
+        None => String::from(label),
+    };
+    uri.path_segments_mut()
+        .expect("Failed to set otpauth URI label")
+        .push(&label);
+
+    {
+        let mut query = uri.query_pairs_mut();
+        query.append_pair("secret", &base32_encode(secret));
+        if let Some(issuer) = issuer {
+            query.append_pair("issuer", issuer);
+        }
+        for (key, value) in params {
+            query.append_pair(key, value);
+        }
+    }
+    uri.into()
 }
 
 /// Parses an otpauth URI.
@@ -113,14 +791,18 @@ pub enum ParseError {
 ///
 /// This method is safe and shouldn't panic. It will return an error if the
 /// provided uri is invalid.
+///
+/// A `digits` or `period` parameter that isn't a number gives a
+/// [`ParseError::MalformedUri`] error. A digit count outside of 1 to
+/// [`MAX_DIGITS`] gives a [`ParseError::WrongDigitNumber`] error, and a
+/// period of 0 gives a [`ParseError::InvalidPeriod`] error.
 pub fn parse_otpauth_uri(uri: &str) -> Result<ParseResult, ParseError> {
     use ParseError::*;
 
-    let parsed_uri = Url::parse(uri);
-    if parsed_uri.is_err() {
-        return Err(UriParseError(parsed_uri.unwrap_err()));
-    }
-    let parsed_uri = parsed_uri.unwrap();
+    let parsed_uri = match Url::parse(uri) {
+        Ok(x) => x,
+        Err(e) => return Err(UriParseError(e)),
+    };
 
     if !parsed_uri.scheme().eq("otpauth") {
         return Err(WrongScheme(String::from(parsed_uri.scheme())));
@@ -138,14 +820,11 @@ pub fn parse_otpauth_uri(uri: &str) -> Result<ParseResult, ParseError> {
 
     let digits = match query.get("digits") {
         Some(x) => match x.parse::<u32>() {
-            Ok(i) => {
-                if i == 0 {
-                    return Err(WrongDigitNumber(String::from(x.as_ref())));
-                } else {
-                    i
-                }
-            }
-            Err(_) => return Err(WrongDigitNumber(String::from(x.as_ref()))),
+            Ok(i) => match validate_digits(i) {
+                Ok(()) => i,
+                Err(_) => return Err(WrongDigitNumber(String::from(x.as_ref()))),
+            },
+            Err(_) => return Err(malformed_param("digits", x)),
         },
         None => 6,
     };
@@ -157,30 +836,25 @@ pub fn parse_otpauth_uri(uri: &str) -> Result<ParseResult, ParseError> {
 
     if type_str.eq("totp") {
         let algo = match query.get("algorithm") {
-            Some(x) => match x.as_ref() {
-                "SHA1" => MacDigest::SHA1,
-                "SHA256" => MacDigest::SHA256,
-                "SHA512" => MacDigest::SHA512,
-                _ => return Err(UnknownAlgorithm(String::from(x.as_ref()))),
+            Some(x) => match parse_algorithm(x) {
+                Some(algo) => algo,
+                None => return Err(UnknownAlgorithm(String::from(x.as_ref()))),
             },
             None => MacDigest::SHA1,
         };
 
         let period = match query.get("period") {
             Some(x) => match x.parse::<u64>() {
-                Ok(i) => {
-                    if i == 0 {
-                        return Err(InvalidPeriod(String::from(x.as_ref())));
-                    } else {
-                        i
-                    }
-                }
-                Err(_) => return Err(InvalidPeriod(String::from(x.as_ref()))),
+                Ok(i) => match validate_period(i) {
+                    Ok(()) => i,
+                    Err(_) => return Err(InvalidPeriod(String::from(x.as_ref()))),
+                },
+                Err(_) => return Err(malformed_param("period", x)),
             },
             None => 30,
         };
 
-        Ok(ParseResult::TOTP(TOTP::new(&secret, algo, digits, period)))
+        Ok(ParseResult::TOTP(TOTP::from_vec(secret, algo, digits, period)))
     } else if type_str.eq("hotp") {
         let counter = match query.get("counter") {
             Some(x) => match x.parse::<u64>() {
@@ -190,8 +864,86 @@ pub fn parse_otpauth_uri(uri: &str) -> Result<ParseResult, ParseError> {
             None => return Err(MissingCounter),
         };
 
-        Ok(ParseResult::HOTP(HOTP::new(&secret, digits), counter))
+        Ok(ParseResult::HOTP(HOTP::from_vec(secret, digits), counter))
     } else {
         Err(UnknownOtpType(String::from(type_str)))
     }
 }
+
+/// Returns the error for an otpauth URI parameter that isn't a number.
+fn malformed_param(param: &str, value: &str) -> ParseError {
+    ParseError::MalformedUri {
+        param: String::from(param),
+        value: String::from(value),
+    }
+}
+
+/// Checks whether two otpauth URIs have the same configuration.
+///
+/// The URIs are compared on their type, secret, algorithm, digits and
+/// period, ignoring their label and issuer. HOTP URIs are also compared
+/// regardless of their counter. This is useful to find duplicate accounts
+/// when importing, as the same secret may be given different labels.
+///
+/// This method is safe and shouldn't panic. It will return an
+/// [`OtpError::InvalidUri`] error with the parsing error if either uri is
+/// invalid.
+pub fn uri_config_eq(a: &str, b: &str) -> Result<bool, OtpError> {
+    let parse = |uri| parse_otpauth_uri(uri).map_err(OtpError::InvalidUri);
+    let eq = match (parse(a)?, parse(b)?) {
+        (ParseResult::TOTP(a), ParseResult::TOTP(b)) => {
+            a.get_secret() == b.get_secret()
+                && a.get_digest() == b.get_digest()
+                && a.get_digits() == b.get_digits()
+                && a.get_period() == b.get_period()
+        }
+        (ParseResult::HOTP(a, _), ParseResult::HOTP(b, _)) => {
+            a.get_secret() == b.get_secret() && a.get_digits() == b.get_digits()
+        }
+        _ => false,
+    };
+    Ok(eq)
+}
+
+/// Generates `count` distinct random numeric backup codes of `length`
+/// digits each, for enrollments that issue one-time recovery codes.
+///
+/// Backup codes aren't derived from an HMAC like [HOTP] and [TOTP] codes.
+/// Each digit is drawn from the operating system's CSPRNG, and the codes are
+/// zero-padded to `length`. The caller should store only a hash of each
+/// code, and accept each one at most once.
+///
+/// # Errors
+/// Returns [`OtpError::InvalidDigits`] if `length` isn't between 1 and
+/// [`MAX_DIGITS`], and [`OtpError::TooManyBackupCodes`] if more codes are
+/// requested than there are codes of that length (10 to the power of
+/// `length`).
+///
+/// # Panics
+/// This method panics if the operating system's random number generator
+/// fails.
+#[cfg(feature = "rand")]
+pub fn generate_backup_codes(count: usize, length: usize) -> Result<Vec<String>, OtpError> {
+    use rand::rngs::OsRng;
+    use rand::Rng;
+    use std::collections::HashSet;
+
+    let digits = u32::try_from(length).unwrap_or(u32::MAX);
+    validate_digits(digits)?;
+    let possible = 10u64.pow(digits);
+    if count as u64 > possible {
+        return Err(OtpError::TooManyBackupCodes { count, possible });
+    }
+
+    let mut seen = HashSet::with_capacity(count);
+    let mut codes = Vec::with_capacity(count);
+    while codes.len() < count {
+        let code: String = (0..length)
+            .map(|_| char::from(b'0' + OsRng.gen_range(0..10)))
+            .collect();
+        if seen.insert(code.clone()) {
+            codes.push(code);
+        }
+    }
+    Ok(codes)
+}
@@ -0,0 +1,96 @@
+// JavaScript bindings for TOTP generation, verification and provisioning
+
+use wasm_bindgen::prelude::*;
+
+use crate::totp::TOTP;
+use crate::util::{base32_decode, parse_algorithm, validate_digits, validate_period};
+
+/// A [`TOTP`] generator exported to JavaScript as `TOTP`.
+///
+/// All methods only use types wasm-bindgen can pass across the boundary:
+/// secrets are given as base32 strings, times, periods and window sizes as
+/// plain numbers (not `bigint`s), and failures are reported as `undefined`
+/// instead of an error type.
+#[wasm_bindgen(js_name = TOTP)]
+pub struct WasmTotp {
+    totp: TOTP,
+}
+
+#[wasm_bindgen(js_class = TOTP)]
+impl WasmTotp {
+    /// Creates a new generator from a base32-encoded secret, an algorithm
+    /// name (`SHA1`, `SHA256` or `SHA512`), a number of digits, and a period
+    /// in seconds.
+    ///
+    /// Returns `undefined` if the secret isn't correctly base32-encoded, the
+    /// algorithm is unknown, or the digit count or period are invalid.
+    #[wasm_bindgen(js_name = fromBase32)]
+    pub fn from_base32(
+        secret: &str,
+        algorithm: &str,
+        digits: u32,
+        period: u32,
+    ) -> Option<WasmTotp> {
+        let mac_digest = parse_algorithm(algorithm)?;
+        validate_digits(digits).ok()?;
+        validate_period(period as u64).ok()?;
+        let secret = base32_decode(secret)?;
+        let totp = TOTP::from_vec(secret, mac_digest, digits, period as u64);
+        Some(WasmTotp { totp })
+    }
+
+    /// Generates the zero-padded code for the specified unix time in
+    /// seconds.
+    ///
+    /// Fractional seconds are truncated, so `Date.now() / 1000` can be passed
+    /// as-is. Returns `undefined` instead of throwing if the time isn't a
+    /// finite, non-negative number or the code can't be generated (see
+    /// [`TOTP::try_get_otp`]).
+    #[wasm_bindgen(js_name = getOtp)]
+    pub fn get_otp(&self, time: f64) -> Option<String> {
+        let time = time_from_js(time)?;
+        self.totp.try_get_otp(time).ok().map(|otp| otp.as_string())
+    }
+
+    /// Checks whether the given code is valid for the specified unix time,
+    /// accepting codes up to `ahead` time-steps after and `behind` time-steps
+    /// before the current one.
+    ///
+    /// Works like [`TOTP::verify`], with the time given like in
+    /// [`WasmTotp::get_otp`]. Returns false if the time isn't a finite,
+    /// non-negative number.
+    pub fn verify(&self, code: &str, time: f64, ahead: u32, behind: u32) -> bool {
+        match time_from_js(time) {
+            Some(time) => self.totp.verify(code, time, ahead as u64, behind as u64),
+            None => false,
+        }
+    }
+
+    /// Returns the `otpauth://totp/` URI of the generator, for the given
+    /// account label and optional issuer.
+    ///
+    /// Works like [`TOTP::to_uri`].
+    #[wasm_bindgen(js_name = toUri)]
+    pub fn to_uri(&self, label: &str, issuer: Option<String>) -> String {
+        self.totp.to_uri(label, issuer.as_deref())
+    }
+}
+
+/// Conversions between [`WasmTotp`] and [`TOTP`] for Rust callers.
+impl From<TOTP> for WasmTotp {
+    fn from(totp: TOTP) -> Self {
+        WasmTotp { totp }
+    }
+}
+
+/// Converts a JavaScript time in seconds into whole unix seconds, dropping
+/// any fractional part.
+///
+/// Returns `None` if the time is NaN, infinite, negative, or too large for
+/// a [`u64`].
+fn time_from_js(time: f64) -> Option<u64> {
+    if !time.is_finite() || time < 0.0 || time >= u64::MAX as f64 {
+        return None;
+    }
+    Some(time.trunc() as u64)
+}
@@ -0,0 +1,171 @@
+#![cfg(feature = "serde")]
+
+use xotp::aegis::parse_aegis_backup;
+use xotp::util::{MacDigest, OtpError, ParseError, ParseResult};
+
+static BACKUP: &str = r#"{
+    "version": 1,
+    "header": { "slots": null, "params": null },
+    "db": {
+        "version": 2,
+        "entries": [
+            {
+                "type": "totp",
+                "uuid": "01234567-89ab-cdef-0123-456789abcdef",
+                "name": "alice@google.com",
+                "issuer": "Example",
+                "info": {
+                    "secret": "JBSWY3DPEHPK3PXP",
+                    "algo": "SHA256",
+                    "digits": 8,
+                    "period": 60
+                }
+            },
+            {
+                "type": "hotp",
+                "uuid": "fedcba98-7654-3210-fedc-ba9876543210",
+                "name": "john.doe@email.com",
+                "issuer": "ACME Co",
+                "info": {
+                    "secret": "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ",
+                    "algo": "SHA1",
+                    "digits": 6,
+                    "counter": 3
+                }
+            }
+        ]
+    }
+}"#;
+
+fn backup_with_entries(entries: &[&str]) -> String {
+    format!(
+        r#"{{ "version": 1, "db": {{ "version": 2, "entries": [{}] }} }}"#,
+        entries.join(", ")
+    )
+}
+
+fn backup_with_entry(entry: &str) -> String {
+    backup_with_entries(&[entry])
+}
+
+// Parses a backup with a single entry and returns that entry's result
+fn parse_entry(entry: &str) -> Result<ParseResult, ParseError> {
+    let mut res = parse_aegis_backup(&backup_with_entry(entry)).unwrap();
+    assert_eq!(res.len(), 1);
+    res.remove(0).otp
+}
+
+#[test]
+fn test_aegis_parse() {
+    let res = parse_aegis_backup(BACKUP).unwrap();
+    assert_eq!(res.len(), 2);
+    assert_eq!(res[0].name, "alice@google.com");
+    assert_eq!(res[0].issuer.as_deref(), Some("Example"));
+    if let Ok(ParseResult::TOTP(totp)) = &res[0].otp {
+        assert_eq!(totp.get_digest(), MacDigest::SHA256);
+        assert_eq!(totp.get_digits(), 8);
+        assert_eq!(totp.get_period(), 60);
+    } else {
+        panic!();
+    }
+    assert_eq!(res[1].name, "john.doe@email.com");
+    assert_eq!(res[1].issuer.as_deref(), Some("ACME Co"));
+    if let Ok(ParseResult::HOTP(hotp, counter)) = &res[1].otp {
+        assert_eq!(hotp.get_digits(), 6);
+        assert_eq!(*counter, 3);
+        // RFC4226 test case 4
+        assert_eq!(hotp.get_otp(*counter).as_u32(), 969429);
+    } else {
+        panic!();
+    }
+}
+
+#[test]
+fn test_aegis_parse_defaults() {
+    let res = parse_aegis_backup(&backup_with_entry(
+        r#"{ "type": "totp", "issuer": "", "info": { "secret": "JBSWY3DPEHPK3PXP" } }"#,
+    ))
+    .unwrap();
+    assert_eq!(res[0].name, "");
+    assert_eq!(res[0].issuer, None);
+    if let Ok(ParseResult::TOTP(totp)) = &res[0].otp {
+        assert_eq!(totp.get_digest(), MacDigest::SHA1);
+        assert_eq!(totp.get_digits(), 6);
+        assert_eq!(totp.get_period(), 30);
+    } else {
+        panic!();
+    }
+}
+
+// Unsupported entries are reported without failing the rest of the import
+#[test]
+fn test_aegis_parse_partial() {
+    let res = parse_aegis_backup(&backup_with_entries(&[
+        r#"{ "type": "steam", "name": "gaben", "info": { "secret": "JBSWY3DPEHPK3PXP" } }"#,
+        r#"{ "type": "totp", "name": "alice", "info": { "secret": "JBSWY3DPEHPK3PXP" } }"#,
+    ]))
+    .unwrap();
+    assert_eq!(res.len(), 2);
+    assert_eq!(res[0].name, "gaben");
+    assert!(matches!(&res[0].otp, Err(ParseError::UnknownOtpType(x)) if x == "steam"));
+    assert_eq!(res[1].name, "alice");
+    assert!(matches!(res[1].otp, Ok(ParseResult::TOTP(_))));
+}
+
+#[test]
+fn test_aegis_parse_unknown_algorithm() {
+    let res = parse_entry(r#"{ "type": "totp", "info": { "secret": "JBSWY3DPEHPK3PXP", "algo": "MD5" } }"#);
+    assert!(matches!(res, Err(ParseError::UnknownAlgorithm(x)) if x == "MD5"));
+}
+
+#[test]
+fn test_aegis_parse_invalid_digits() {
+    let res = parse_entry(r#"{ "type": "totp", "info": { "secret": "JBSWY3DPEHPK3PXP", "digits": 100 } }"#);
+    assert!(matches!(res, Err(ParseError::WrongDigitNumber(x)) if x == "100"));
+    let res = parse_entry(
+        r#"{ "type": "hotp", "info": { "secret": "JBSWY3DPEHPK3PXP", "digits": 0, "counter": 1 } }"#,
+    );
+    assert!(matches!(res, Err(ParseError::WrongDigitNumber(x)) if x == "0"));
+}
+
+#[test]
+fn test_aegis_parse_invalid_period() {
+    let res = parse_entry(r#"{ "type": "totp", "info": { "secret": "JBSWY3DPEHPK3PXP", "period": 0 } }"#);
+    assert!(matches!(res, Err(ParseError::InvalidPeriod(x)) if x == "0"));
+}
+
+#[test]
+fn test_aegis_parse_unknown_type() {
+    let res = parse_entry(r#"{ "type": "steam", "info": { "secret": "JBSWY3DPEHPK3PXP" } }"#);
+    assert!(matches!(res, Err(ParseError::UnknownOtpType(x)) if x == "steam"));
+}
+
+#[test]
+fn test_aegis_parse_missing_counter() {
+    let res = parse_entry(r#"{ "type": "hotp", "info": { "secret": "JBSWY3DPEHPK3PXP" } }"#);
+    assert!(matches!(res, Err(ParseError::MissingCounter)));
+}
+
+#[test]
+fn test_aegis_parse_invalid_secret() {
+    let res = parse_entry(r#"{ "type": "totp", "info": { "secret": "not base32" } }"#);
+    assert!(matches!(res, Err(ParseError::SecretParsingError(x)) if x == "not base32"));
+}
+
+#[test]
+fn test_aegis_parse_encrypted() {
+    let res = parse_aegis_backup(r#"{ "version": 1, "db": "c2VjcmV0" }"#);
+    assert!(matches!(res.unwrap_err(), OtpError::MalformedBackup { .. }));
+}
+
+#[test]
+fn test_aegis_parse_invalid_json() {
+    let res = parse_aegis_backup("not json");
+    assert!(matches!(res.unwrap_err(), OtpError::MalformedBackup { .. }));
+}
+
+#[test]
+fn test_aegis_parse_missing_entries() {
+    let res = parse_aegis_backup(r#"{ "version": 1, "db": { "version": 2 } }"#);
+    assert!(matches!(res.unwrap_err(), OtpError::MalformedBackup { .. }));
+}
@@ -1,4 +1,5 @@
-use xotp::hotp::HOTP;
+use xotp::hotp::{HotpWindow, HOTP};
+use xotp::util::{OtpError, TruncationOffset};
 
 static SECRET_UTF8: &str = "12345678901234567890";
 static SECRET_BYTES: &[u8] = SECRET_UTF8.as_bytes();
@@ -79,3 +80,426 @@ fn rfc_test_case_9() {
 fn rfc_test_case_10() {
     assert_eq!(run_rfc_test_base32(9), 520489)
 }
+
+// The RFC4226 HMAC for count 0 selects the bytes 0xcc93cf18, whose high
+// bit is set. Masking it gives 0x4c93cf18 (1284755224), while an unmasked
+// read would give 3432238872 and a 9-digit code of 432238872.
+#[test]
+fn test_sign_bit_masked() {
+    let hotp = HOTP::new(SECRET_BYTES, 9);
+    assert_eq!(hotp.get_otp(0).as_u32(), 284755224)
+}
+
+// Tests to check the otpauth URI conversions.
+#[test]
+fn test_to_uri() {
+    let hotp = HOTP::new(SECRET_BYTES, 6);
+    assert_eq!(
+        hotp.hotp_to_uri("john.doe@email.com", Some("ACME Co"), 1234),
+        "otpauth://hotp/ACME%20Co:john.doe@email.com?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&issuer=ACME+Co&digits=6&counter=1234"
+    );
+}
+
+#[test]
+fn test_uri_round_trip() {
+    let hotp = HOTP::new(SECRET_BYTES, 8);
+    let uri = hotp.hotp_to_uri("john.doe@email.com", None, 7);
+    let (parsed, counter) = HOTP::from_uri(&uri).unwrap();
+    assert_eq!(counter, 7);
+    assert_eq!(parsed.get_digits(), 8);
+    assert_eq!(parsed.get_otp(counter), hotp.get_otp(7));
+}
+
+#[test]
+fn test_from_uri_totp() {
+    let res = HOTP::from_uri(
+        "otpauth://totp/Example:alice@google.com?secret=JBSWY3DPEHPK3PXP&issuer=Example",
+    );
+    assert!(res.is_err());
+}
+
+// FromStr accepts either a full otpauth URI, whose counter is dropped, or
+// a bare base32 secret
+#[test]
+fn test_from_str_uri() {
+    let hotp: HOTP = "otpauth://hotp/alice?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&digits=8&counter=7"
+        .parse()
+        .unwrap();
+    assert_eq!(hotp.get_digits(), 8);
+    assert_eq!(hotp.get_otp(7), HOTP::new(SECRET_BYTES, 8).get_otp(7));
+}
+
+#[test]
+fn test_from_str_base32() {
+    let hotp: HOTP = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ".parse().unwrap();
+    assert_eq!(hotp.get_digits(), 6);
+    assert_eq!(hotp.get_otp(1).as_string(), "287082");
+}
+
+#[test]
+fn test_from_str_invalid() {
+    assert!("not a secret".parse::<HOTP>().is_err());
+    assert!("".parse::<HOTP>().is_err());
+    assert!(" \t".parse::<HOTP>().is_err());
+    assert!("otpauth://totp/alice?secret=JBSWY3DPEHPK3PXP".parse::<HOTP>().is_err());
+}
+
+// A secret longer than the SHA1 block size (64 bytes) is hashed before
+// being used as the HMAC key. The expected codes come from Python's
+// reference `hmac` module.
+#[test]
+fn test_secret_longer_than_block_size() {
+    let secret: Vec<u8> = (0..100).collect();
+    let hotp = HOTP::new(&secret, 6);
+    assert_eq!(hotp.get_otp(0).as_string(), "061198");
+    assert_eq!(hotp.get_otp(1).as_string(), "695482");
+    assert_eq!(hotp.get_otp(2).as_string(), "115445");
+}
+
+#[test]
+fn test_secret_longer_than_block_size_is_hashed() {
+    use sha1::{Digest, Sha1};
+
+    let secret: Vec<u8> = (0..100).collect();
+    let hashed_secret = Sha1::digest(&secret);
+    assert_eq!(
+        HOTP::new(&secret, 6).get_otp(0),
+        HOTP::new(&hashed_secret, 6).get_otp(0)
+    );
+}
+
+#[test]
+fn test_from_vec() {
+    let hotp = HOTP::from_vec(SECRET_BYTES.to_vec(), 6);
+    assert_eq!(hotp.get_otp(0).as_u32(), 755224)
+}
+
+#[test]
+fn test_codes() {
+    let hotp = HOTP::new(SECRET_BYTES, 6);
+    let codes = hotp.codes(3, 5);
+    assert_eq!(codes.len(), 5);
+    for (i, (counter, otp)) in codes.iter().enumerate() {
+        assert_eq!(*counter, 3 + i as u64);
+        assert_eq!(*otp, hotp.get_otp(*counter));
+    }
+    assert_eq!(codes[0].1.as_u32(), 969429);
+}
+
+#[test]
+fn test_codes_counter_overflow() {
+    let hotp = HOTP::new(SECRET_BYTES, 6);
+    let codes = hotp.codes(u64::MAX - 1, 5);
+    assert_eq!(codes.len(), 2);
+    assert_eq!(codes[1].0, u64::MAX);
+}
+
+// Tests to check the bidirectional verification window.
+#[test]
+fn test_verify_window_bidirectional_behind() {
+    let hotp = HOTP::new(SECRET_BYTES, 6);
+    assert_eq!(hotp.verify_window_bidirectional("755224", 2, 5, 0), Some(0));
+    assert_eq!(hotp.verify_window_bidirectional("287082", 2, 5, 0), Some(1));
+}
+
+#[test]
+fn test_verify_window_bidirectional_ahead() {
+    let hotp = HOTP::new(SECRET_BYTES, 6);
+    assert_eq!(hotp.verify_window_bidirectional("520489", 2, 5, 7), Some(9));
+    assert_eq!(hotp.verify_window_bidirectional("520489", 2, 5, 6), None);
+}
+
+#[test]
+fn test_verify_window_bidirectional_upper_bound() {
+    let hotp = HOTP::new(SECRET_BYTES, 6);
+    assert_eq!(hotp.verify_window_bidirectional("000000", u64::MAX, 1, 5), None);
+}
+
+// Huge windows are capped instead of allocating every counter
+#[test]
+fn test_verify_window_bidirectional_huge() {
+    let hotp = HOTP::new(SECRET_BYTES, 6);
+    assert_eq!(hotp.verify_window_bidirectional("000000", u64::MAX / 2, u64::MAX, 0), None);
+    assert_eq!(hotp.verify_window_bidirectional("520489", 2, u64::MAX, u64::MAX), Some(9));
+}
+
+#[test]
+fn test_verify_window_bidirectional_full_width() {
+    let hotp = HOTP::new(SECRET_BYTES, 6);
+    assert_eq!(hotp.verify_window_bidirectional("７５５２２４", 2, 5, 0), Some(0));
+}
+
+// Tests to check the truncation offset settings. The count 1 HMAC has a
+// dynamic offset of 11, so a fixed offset of 0 selects different bytes.
+#[test]
+fn test_truncation_offset_fixed() {
+    let hotp = HOTP::new(SECRET_BYTES, 6);
+    assert_eq!(hotp.get_truncation_offset(), TruncationOffset::Dynamic);
+    assert_eq!(hotp.get_otp(1).as_string(), "287082");
+
+    let hotp = hotp
+        .with_truncation_offset(TruncationOffset::Fixed(0))
+        .unwrap();
+    assert_eq!(hotp.get_otp(1).as_string(), "717529");
+    assert_eq!(hotp.get_otp(2).as_string(), "868666");
+}
+
+#[test]
+fn test_truncation_offset_fixed_last() {
+    let hotp = HOTP::new(SECRET_BYTES, 6)
+        .with_truncation_offset(TruncationOffset::Fixed(16))
+        .unwrap();
+    assert_eq!(hotp.get_otp(1).as_string(), "782699");
+}
+
+#[test]
+fn test_truncation_offset_fixed_invalid() {
+    let res = HOTP::new(SECRET_BYTES, 6).with_truncation_offset(TruncationOffset::Fixed(17));
+    assert_eq!(res.unwrap_err(), OtpError::InvalidTruncationOffset(17));
+}
+
+#[cfg(feature = "zeroize")]
+#[test]
+fn test_zeroizing_clone() {
+    let hotp = HOTP::new(SECRET_BYTES, 6);
+    let cloned = hotp.clone();
+    drop(hotp);
+    assert_eq!(cloned.get_otp(0).as_string(), "755224")
+}
+
+// Tests for the non-panicking generation
+#[test]
+fn test_try_get_otp() {
+    let hotp = HOTP::new(SECRET_BYTES, 6);
+    for counter in [0, 1, u64::MAX] {
+        assert_eq!(hotp.try_get_otp(counter), Ok(hotp.get_otp(counter)));
+    }
+}
+
+#[test]
+fn test_try_get_otp_too_many_digits() {
+    let hotp = HOTP::new(&[], 20);
+    assert_eq!(hotp.try_get_otp(0), Err(OtpError::InvalidDigits(20)));
+}
+
+#[cfg(feature = "secrecy")]
+#[test]
+fn test_new_from_secret_base32() {
+    use secrecy::SecretString;
+
+    let secret = SecretString::new(String::from("GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ"));
+    let hotp = HOTP::new_from_secret_base32(&secret, 6).unwrap();
+    assert_eq!(hotp.get_otp(0).as_string(), "755224");
+}
+
+// Tests for the resync with two consecutive codes
+#[test]
+fn test_resync_drifted() {
+    let hotp = HOTP::new(SECRET_BYTES, 6);
+    // The server expects counter 5, but the token is 20 counters ahead
+    let code1 = hotp.get_otp(25).as_string();
+    let code2 = hotp.get_otp(26).as_string();
+    assert_eq!(hotp.resync(&code1, &code2, 5, 100), Some(27));
+}
+
+#[test]
+fn test_resync_rfc_codes() {
+    let hotp = HOTP::new(SECRET_BYTES, 6);
+    assert_eq!(hotp.resync("969429", "338314", 0, 10), Some(5));
+}
+
+#[test]
+fn test_resync_not_consecutive() {
+    let hotp = HOTP::new(SECRET_BYTES, 6);
+    assert_eq!(hotp.resync("969429", "254676", 0, 10), None);
+}
+
+#[test]
+fn test_resync_out_of_range() {
+    let hotp = HOTP::new(SECRET_BYTES, 6);
+    assert_eq!(hotp.resync("969429", "338314", 4, 10), None);
+    assert_eq!(hotp.resync("969429", "338314", 0, 3), None);
+    assert_eq!(hotp.resync("969429", "338314", 0, 0), None);
+}
+
+// Cross-validation against vectors in the `oathtool` format, with lines of
+// `secret_hex counter expected_code`. Each line is checked against the
+// output of
+//
+//     oathtool --hotp -d <digits> -c <counter> <secret_hex>
+//
+// where the digit count is the length of the expected code, by the ignored
+// `test_oath_vectors_against_oathtool` test. Run it with oathtool installed
+// through `cargo test --test hotp -- --ignored`. The secrets have other
+// lengths than the 20 bytes of the RFC4226 secret, and the counters go up
+// to u64::MAX.
+static OATH_VECTORS: &str = "
+# 1-byte secret
+00 0 328482
+00 1 0812658
+00 255 49105833
+00 4294967296 001693
+00 9223372036854775808 4943357
+00 18446744073709551615 99566304
+
+# 10-byte secret
+0102030405060708090a 0 806499
+0102030405060708090a 1 9031563
+0102030405060708090a 255 65216189
+0102030405060708090a 4294967296 856205
+0102030405060708090a 9223372036854775808 4468422
+0102030405060708090a 18446744073709551615 98837486
+
+# 16-byte secret
+00112233445566778899aabbccddeeff 0 166448
+00112233445566778899aabbccddeeff 1 8738396
+00112233445566778899aabbccddeeff 255 99809063
+00112233445566778899aabbccddeeff 4294967296 196689
+00112233445566778899aabbccddeeff 9223372036854775808 7383779
+00112233445566778899aabbccddeeff 18446744073709551615 04235034
+
+# 32-byte secret
+030a11181f262d343b424950575e656c737a81888f969da4abb2b9c0c7ced5dc 0 652859
+030a11181f262d343b424950575e656c737a81888f969da4abb2b9c0c7ced5dc 1 5869302
+030a11181f262d343b424950575e656c737a81888f969da4abb2b9c0c7ced5dc 255 62573921
+030a11181f262d343b424950575e656c737a81888f969da4abb2b9c0c7ced5dc 4294967296 102945
+030a11181f262d343b424950575e656c737a81888f969da4abb2b9c0c7ced5dc 9223372036854775808 2180220
+030a11181f262d343b424950575e656c737a81888f969da4abb2b9c0c7ced5dc 18446744073709551615 20107201
+
+# 64-byte secret, the SHA1 block size
+fffefdfcfbfaf9f8f7f6f5f4f3f2f1f0efeeedecebeae9e8e7e6e5e4e3e2e1e0dfdedddcdbdad9d8d7d6d5d4d3d2d1d0cfcecdcccbcac9c8c7c6c5c4c3c2c1c0 0 941279
+fffefdfcfbfaf9f8f7f6f5f4f3f2f1f0efeeedecebeae9e8e7e6e5e4e3e2e1e0dfdedddcdbdad9d8d7d6d5d4d3d2d1d0cfcecdcccbcac9c8c7c6c5c4c3c2c1c0 1 4246876
+fffefdfcfbfaf9f8f7f6f5f4f3f2f1f0efeeedecebeae9e8e7e6e5e4e3e2e1e0dfdedddcdbdad9d8d7d6d5d4d3d2d1d0cfcecdcccbcac9c8c7c6c5c4c3c2c1c0 255 69028376
+fffefdfcfbfaf9f8f7f6f5f4f3f2f1f0efeeedecebeae9e8e7e6e5e4e3e2e1e0dfdedddcdbdad9d8d7d6d5d4d3d2d1d0cfcecdcccbcac9c8c7c6c5c4c3c2c1c0 4294967296 122698
+fffefdfcfbfaf9f8f7f6f5f4f3f2f1f0efeeedecebeae9e8e7e6e5e4e3e2e1e0dfdedddcdbdad9d8d7d6d5d4d3d2d1d0cfcecdcccbcac9c8c7c6c5c4c3c2c1c0 9223372036854775808 2517955
+fffefdfcfbfaf9f8f7f6f5f4f3f2f1f0efeeedecebeae9e8e7e6e5e4e3e2e1e0dfdedddcdbdad9d8d7d6d5d4d3d2d1d0cfcecdcccbcac9c8c7c6c5c4c3c2c1c0 18446744073709551615 59578684
+
+# 100-byte secret, hashed before use as it's longer than the block size
+000d1a2734414e5b6875828f9ca9b6c3d0ddeaf704111e2b3845525f6c798693a0adbac7d4e1eefb0815222f3c495663707d8a97a4b1becbd8e5f2ff0c192633404d5a6774818e9ba8b5c2cfdce9f603101d2a3744515e6b7885929facb9c6d3e0edfa07 0 730243
+000d1a2734414e5b6875828f9ca9b6c3d0ddeaf704111e2b3845525f6c798693a0adbac7d4e1eefb0815222f3c495663707d8a97a4b1becbd8e5f2ff0c192633404d5a6774818e9ba8b5c2cfdce9f603101d2a3744515e6b7885929facb9c6d3e0edfa07 1 8952925
+000d1a2734414e5b6875828f9ca9b6c3d0ddeaf704111e2b3845525f6c798693a0adbac7d4e1eefb0815222f3c495663707d8a97a4b1becbd8e5f2ff0c192633404d5a6774818e9ba8b5c2cfdce9f603101d2a3744515e6b7885929facb9c6d3e0edfa07 255 81915859
+000d1a2734414e5b6875828f9ca9b6c3d0ddeaf704111e2b3845525f6c798693a0adbac7d4e1eefb0815222f3c495663707d8a97a4b1becbd8e5f2ff0c192633404d5a6774818e9ba8b5c2cfdce9f603101d2a3744515e6b7885929facb9c6d3e0edfa07 4294967296 031663
+000d1a2734414e5b6875828f9ca9b6c3d0ddeaf704111e2b3845525f6c798693a0adbac7d4e1eefb0815222f3c495663707d8a97a4b1becbd8e5f2ff0c192633404d5a6774818e9ba8b5c2cfdce9f603101d2a3744515e6b7885929facb9c6d3e0edfa07 9223372036854775808 7065913
+000d1a2734414e5b6875828f9ca9b6c3d0ddeaf704111e2b3845525f6c798693a0adbac7d4e1eefb0815222f3c495663707d8a97a4b1becbd8e5f2ff0c192633404d5a6774818e9ba8b5c2cfdce9f603101d2a3744515e6b7885929facb9c6d3e0edfa07 18446744073709551615 47742656
+";
+
+// Parses `oathtool` vectors, skipping blank lines and `#` comments. The
+// digit count of each vector is the length of its expected code.
+fn parse_oath_vectors(input: &str) -> Vec<(Vec<u8>, u64, String)> {
+    input
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            assert_eq!(fields.len(), 3, "Malformed vector: {}", line);
+            let secret = (0..fields[0].len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(&fields[0][i..i + 2], 16).expect("Invalid hex secret"))
+                .collect();
+            let counter = fields[1].parse().expect("Invalid counter");
+            (secret, counter, String::from(fields[2]))
+        })
+        .collect()
+}
+
+#[test]
+fn test_oath_vectors() {
+    let vectors = parse_oath_vectors(OATH_VECTORS);
+    assert_eq!(vectors.len(), 36);
+    for (secret, counter, expected) in vectors {
+        let hotp = HOTP::new(&secret, expected.len() as u32);
+        assert_eq!(hotp.get_otp(counter).as_string(), expected);
+    }
+}
+
+// Runs the command each vector was generated with, and checks that
+// oathtool still outputs the expected code. Ignored by default, as it
+// needs oathtool to be installed.
+#[test]
+#[ignore]
+fn test_oath_vectors_against_oathtool() {
+    for (secret, counter, expected) in parse_oath_vectors(OATH_VECTORS) {
+        let secret_hex: String = secret.iter().map(|byte| format!("{:02x}", byte)).collect();
+        let output = std::process::Command::new("oathtool")
+            .args(["--hotp", "-d", &expected.len().to_string(), "-c", &counter.to_string(), &secret_hex])
+            .output()
+            .expect("Failed to run oathtool");
+        assert!(output.status.success(), "oathtool failed for {} {}", secret_hex, counter);
+        assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), expected);
+    }
+}
+
+// Tests for the HMAC message of a counter
+#[test]
+fn test_moving_factor_bytes() {
+    let hotp = HOTP::new(SECRET_BYTES, 6);
+    assert_eq!(hotp.moving_factor_bytes(1), [0, 0, 0, 0, 0, 0, 0, 1]);
+    assert_eq!(hotp.moving_factor_bytes(0x0102030405060708), [1, 2, 3, 4, 5, 6, 7, 8]);
+}
+
+// Tests for the replay protected counter window
+#[test]
+fn test_hotp_window_accepts() {
+    let mut window = HotpWindow::new(HOTP::new(SECRET_BYTES, 6), 0);
+    assert!(window.verify("755224", 5));
+    assert_eq!(window.get_base_counter(), 1);
+    assert!(window.verify("287082", 5));
+    assert_eq!(window.get_base_counter(), 2);
+}
+
+#[test]
+fn test_hotp_window_rejects_replay() {
+    let mut window = HotpWindow::new(HOTP::new(SECRET_BYTES, 6), 0);
+    assert!(window.verify("969429", 5));
+    assert!(!window.verify("969429", 5));
+    assert!(window.is_used(3));
+    assert!(!window.verify("000000", 5));
+}
+
+#[test]
+fn test_hotp_window_slides() {
+    let mut window = HotpWindow::new(HOTP::new(SECRET_BYTES, 6), 0);
+    // Counter 2 is used ahead of the base, so the window doesn't move yet
+    assert!(window.verify("359152", 5));
+    assert_eq!(window.get_base_counter(), 0);
+    assert!(!window.is_used(1));
+    // The skipped counters are still accepted, and the window then moves
+    // past all used counters
+    assert!(window.verify("287082", 5));
+    assert_eq!(window.get_base_counter(), 0);
+    assert!(window.verify("755224", 5));
+    assert_eq!(window.get_base_counter(), 3);
+    assert!(window.is_used(0) && window.is_used(2) && !window.is_used(3));
+}
+
+#[test]
+fn test_hotp_window_look_ahead() {
+    let mut window = HotpWindow::new(HOTP::new(SECRET_BYTES, 6), 0);
+    assert!(!window.verify("520489", 8));
+    assert!(window.verify("520489", 9));
+    assert_eq!(window.get_base_counter(), 0);
+}
+
+// The base counter can't move past u64::MAX, so the last counter must stay
+// marked as used
+#[test]
+fn test_hotp_window_end_of_range() {
+    let hotp = HOTP::new(SECRET_BYTES, 6);
+    let code = hotp.get_otp(u64::MAX).as_string();
+    let mut window = HotpWindow::new(hotp, u64::MAX);
+    assert!(window.verify(&code, 5));
+    assert_eq!(window.get_base_counter(), u64::MAX);
+    assert!(window.is_used(u64::MAX));
+    assert!(!window.verify(&code, 5));
+
+    let hotp = HOTP::new(SECRET_BYTES, 6);
+    let last = hotp.get_otp(u64::MAX).as_string();
+    let before = hotp.get_otp(u64::MAX - 1).as_string();
+    let mut window = HotpWindow::new(hotp, u64::MAX - 1);
+    assert!(window.verify(&before, 5));
+    assert_eq!(window.get_base_counter(), u64::MAX);
+    assert!(window.verify(&last, 5));
+    assert!(!window.verify(&last, 5));
+    assert!(!window.verify(&before, 5));
+}
@@ -1,4 +1,5 @@
 use xotp::otp_result::OTPResult;
+use xotp::util::OtpError;
 
 // Tests whether a code with less than 6 digits adds on leading zeroes
 #[test]
@@ -12,4 +13,183 @@ fn test_padding_needed() {
 fn test_padding_not_needed() {
     let result = OTPResult::new(6, 123456);
     assert_eq!("123456", result.as_string())
-}
\ No newline at end of file
+}
+
+// Tests whether the grouped formatter splits even digit counts in half
+#[test]
+fn test_formatted_6_digits() {
+    let result = OTPResult::new(6, 123456);
+    assert_eq!("123 456", result.formatted())
+}
+
+#[test]
+fn test_formatted_8_digits() {
+    let result = OTPResult::new(8, 12345678);
+    assert_eq!("1234 5678", result.formatted())
+}
+
+// Tests whether the grouped formatter leaves odd digit counts as-is
+#[test]
+fn test_formatted_7_digits() {
+    let result = OTPResult::new(7, 1234567);
+    assert_eq!("1234567", result.formatted())
+}
+
+// Tests whether the grouped formatter keeps leading zeroes
+#[test]
+fn test_formatted_padding() {
+    let result = OTPResult::new(6, 1234);
+    assert_eq!("001 234", result.formatted())
+}
+
+// Tests whether the serialized result contains both the code and the
+// padded string, and whether it deserializes back to the same result
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde_round_trip() {
+    let result = OTPResult::new(6, 42315);
+    let json = serde_json::to_string(&result).unwrap();
+    assert_eq!(r#"{"code":42315,"digits":6,"formatted":"042315"}"#, json);
+    assert_eq!(result, serde_json::from_str::<OTPResult>(&json).unwrap())
+}
+
+// Tests whether deserialization ignores the formatted string
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde_ignores_formatted() {
+    let json = r#"{"code":42315,"digits":8,"formatted":"042315"}"#;
+    let result: OTPResult = serde_json::from_str(json).unwrap();
+    assert_eq!("00042315", result.as_string())
+}
+
+// Tests whether the digits are written zero-padded into a larger buffer
+#[test]
+fn test_write_digits() {
+    let result = OTPResult::new(6, 1234);
+    let mut buf = [b' '; 8];
+    assert_eq!(Ok(6), result.write_digits(&mut buf));
+    assert_eq!(b"001234  ", &buf)
+}
+
+// Tests whether writing into a buffer that is too small fails
+#[test]
+fn test_write_digits_buffer_too_small() {
+    let result = OTPResult::new(6, 123456);
+    let mut buf = [0; 4];
+    assert_eq!(
+        Err(OtpError::BufferTooSmall {
+            needed: 6,
+            available: 4
+        }),
+        result.write_digits(&mut buf)
+    );
+    assert_eq!([0; 4], buf)
+}
+
+// Tests whether a code with more digits than the digit count is written in
+// full, like as_string
+#[test]
+fn test_write_digits_long_code() {
+    let result = OTPResult::new(2, 12345);
+    let mut buf = [b' '; 6];
+    assert_eq!(Ok(5), result.write_digits(&mut buf));
+    assert_eq!(b"12345 ", &buf);
+    assert_eq!("12345", result.as_string());
+    let mut buf = [0; 4];
+    assert_eq!(
+        Err(OtpError::BufferTooSmall {
+            needed: 5,
+            available: 4
+        }),
+        result.write_digits(&mut buf)
+    );
+}
+
+// Tests whether a code is displayed in another radix with padding
+#[test]
+fn test_as_radix_string() {
+    let result = OTPResult::new(6, 255);
+    assert_eq!("0000ff", result.as_radix_string(16));
+    assert_eq!("11111111", result.as_radix_string(2));
+    assert_eq!("000255", result.as_radix_string(10))
+}
+
+// Tests whether a result created with a radix is displayed in it
+#[test]
+fn test_new_with_radix() {
+    let result = OTPResult::new_with_radix(6, 0xbeef, 16);
+    assert_eq!(16, result.get_radix());
+    assert_eq!("00beef", result.as_string());
+    let mut buf = [0; 6];
+    assert_eq!(Ok(6), result.write_digits(&mut buf));
+    assert_eq!(b"00beef", &buf)
+}
+
+// Tests whether a result with a radix round trips through serde
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde_radix_round_trip() {
+    let result = OTPResult::new_with_radix(6, 0xbeef, 16);
+    let json = serde_json::to_string(&result).unwrap();
+    assert_eq!(r#"{"code":48879,"digits":6,"radix":16,"formatted":"00beef"}"#, json);
+    assert_eq!(result, serde_json::from_str::<OTPResult>(&json).unwrap())
+}
+
+// Tests whether an out-of-range radix is a deserialization error instead
+// of a panic
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde_invalid_radix() {
+    assert!(serde_json::from_str::<OTPResult>(r#"{"code":1,"digits":6,"radix":99}"#).is_err());
+    assert!(serde_json::from_str::<OTPResult>(r#"{"code":1,"digits":6,"radix":1}"#).is_err());
+}
+
+// Tests whether the code comparison ignores the digit count, unlike ==
+#[test]
+fn test_same_code_as() {
+    let six_digits = OTPResult::new(6, 42315);
+    let eight_digits = OTPResult::new(8, 42315);
+    assert!(six_digits.same_code_as(&eight_digits));
+    assert_ne!(six_digits, eight_digits)
+}
+
+#[test]
+fn test_not_same_code_as() {
+    let first = OTPResult::new(6, 42315);
+    let second = OTPResult::new(6, 42316);
+    assert!(!first.same_code_as(&second));
+    assert_eq!(first, OTPResult::new(6, 42315))
+}
+
+// Tests for the code input matching with Unicode digit variants
+#[test]
+fn test_matches_ascii() {
+    let result = OTPResult::new(6, 42315);
+    assert!(result.matches("042315"));
+    assert!(!result.matches("42315"));
+    assert!(!result.matches("042316"));
+    assert!(!result.matches("142315"));
+    assert!(!result.matches(""));
+    assert!(!result.matches("0423150"));
+}
+
+#[test]
+fn test_matches_full_width() {
+    let result = OTPResult::new(6, 42315);
+    assert!(result.matches("０４２３１５"));
+}
+
+#[test]
+fn test_matches_arabic_indic() {
+    let result = OTPResult::new(6, 42315);
+    assert!(result.matches("٠٤٢٣١٥"));
+    assert!(result.matches("۰۴۲۳۱۵"));
+    assert!(result.matches("04２3١5"));
+}
+
+#[test]
+fn test_matches_other_unicode_digits() {
+    // Devanagari digits aren't converted
+    let result = OTPResult::new(6, 42315);
+    assert!(!result.matches("०४२३१५"));
+}
@@ -1,5 +1,8 @@
-use xotp::totp::TOTP;
-use xotp::util::MacDigest;
+use std::time::Duration;
+use xotp::totp::{
+    CodeValidity, ConfigWarning, SkewHistogram, TotpConfig, UnderflowPolicy, TOTP,
+};
+use xotp::util::{MacDigest, OtpError, TruncationOffset};
 
 // RFC6238 SHA1 Secret
 static SECRET_UTF8_SHA1: &str = "12345678901234567890";
@@ -87,7 +90,7 @@ fn rfc_test_1_sha1() {
 
 #[test]
 fn rfc_test_2_sha1() {
-    assert_eq!(run_rfc_test_bytes(1111111109), 07081804)
+    assert_eq!(run_rfc_test_bytes(1111111109), 7081804) // RFC value: 07081804
 }
 
 // Tests 3-4 ran with 'SECRET_UTF8_SHA1'
@@ -219,24 +222,1154 @@ fn rfc_test_6_sha512() {
 // Tests to check the time_until_refresh methods.
 #[test]
 fn test_time_until() {
-    let totp = TOTP::default_from_base32("SecretKey");
+    let totp = TOTP::default_from_base32(SECRET_BASE32_SHA1);
     assert_eq!(totp.time_until_refresh(15), 15);
 }
 
 #[test]
 fn test_time_until_at_edge() {
-    let totp = TOTP::default_from_base32("SecretKey");
+    let totp = TOTP::default_from_base32(SECRET_BASE32_SHA1);
     assert_eq!(totp.time_until_refresh(30), 30)
 }
 
 #[test]
 fn test_time_until_with_start() {
-    let totp = TOTP::default_from_base32("SecretKey");
+    let totp = TOTP::default_from_base32(SECRET_BASE32_SHA1);
     assert_eq!(totp.time_until_refresh_with_start(30, 15), 15)
 }
 
 #[test]
 fn test_time_until_with_start_at_edge() {
-    let totp = TOTP::default_from_base32("SecretKey");
+    let totp = TOTP::default_from_base32(SECRET_BASE32_SHA1);
     assert_eq!(totp.time_until_refresh_with_start(45, 15), 30)
-}
\ No newline at end of file
+}
+#[test]
+fn test_time_until_mid_period() {
+    let totp = TOTP::default_from_base32(SECRET_BASE32_SHA1);
+    assert_eq!(totp.time_until_refresh(40), 20)
+}
+
+// Tests to check the combined code and countdown getter.
+#[test]
+fn test_otp_and_remaining_at_edge() {
+    let totp = TOTP::new(SECRET_BYTES_SHA1, MacDigest::SHA1, 8, 30);
+    let (otp, remaining) = totp.get_otp_and_remaining(1111111110);
+    assert_eq!(otp, totp.get_otp(1111111111));
+    assert_eq!(otp.as_u32(), 14050471);
+    assert_eq!(remaining, 30)
+}
+
+#[test]
+fn test_otp_and_remaining_before_edge() {
+    let totp = TOTP::new(SECRET_BYTES_SHA1, MacDigest::SHA1, 8, 30);
+    let (otp, remaining) = totp.get_otp_and_remaining(1111111109);
+    assert_eq!(otp.as_u32(), 7081804);
+    assert_eq!(remaining, 1)
+}
+
+// Tests to check a TOTP instance with a custom epoch (T0).
+#[test]
+fn test_custom_epoch() {
+    let totp = TOTP::new_with_epoch(SECRET_BYTES_SHA1, MacDigest::SHA1, 8, 30, 1000);
+    assert_eq!(totp.get_epoch(), 1000);
+    assert_eq!(totp.get_otp(1059).as_u32(), 94287082)
+}
+
+#[test]
+fn test_custom_epoch_with_start() {
+    let totp = TOTP::new_with_epoch(SECRET_BYTES_SHA1, MacDigest::SHA1, 8, 30, 1000);
+    assert_eq!(
+        totp.get_otp_with_custom_time_start(1159, 100).as_u32(),
+        94287082
+    )
+}
+
+#[test]
+fn test_default_epoch() {
+    let totp = TOTP::default_from_utf8(SECRET_UTF8_SHA1);
+    assert_eq!(totp.get_epoch(), 0)
+}
+
+// Tests to check the verification methods.
+#[test]
+fn test_verify() {
+    let totp = TOTP::new(SECRET_BYTES_SHA1, MacDigest::SHA1, 8, 30);
+    assert!(totp.verify("94287082", 59, 0, 0));
+    assert!(totp.verify("94287082", 89, 0, 1));
+    assert!(!totp.verify("94287082", 89, 0, 0));
+}
+
+#[test]
+fn test_verify_batch() {
+    let totp = TOTP::new(SECRET_BYTES_SHA1, MacDigest::SHA1, 8, 30);
+    let codes = ["12345678", "94287082", "00000000"];
+    assert_eq!(totp.verify_batch(&codes, 119, 0, 2), Some((1, -2)));
+}
+
+#[test]
+fn test_verify_batch_no_match() {
+    let totp = TOTP::new(SECRET_BYTES_SHA1, MacDigest::SHA1, 8, 30);
+    let codes = ["12345678", "00000000"];
+    assert_eq!(totp.verify_batch(&codes, 59, 1, 1), None);
+}
+
+// Tests to check the named and validating constructors.
+#[test]
+fn test_new_6_digit() {
+    let totp = TOTP::new_6_digit(SECRET_BYTES_SHA1, MacDigest::SHA1, 30);
+    assert_eq!(totp.get_digits(), 6);
+    assert_eq!(totp.get_period(), 30);
+    assert_eq!(totp.get_otp(59).as_string(), "287082")
+}
+
+#[test]
+fn test_new_8_digit() {
+    let totp = TOTP::new_8_digit(SECRET_BYTES_SHA1, MacDigest::SHA1, 30);
+    assert_eq!(totp.get_digits(), 8);
+    assert_eq!(totp.get_otp(59).as_string(), "94287082")
+}
+
+#[test]
+fn test_try_new() {
+    let totp = TOTP::try_new(SECRET_BYTES_SHA1, MacDigest::SHA1, 7, 30).unwrap();
+    assert_eq!(totp.get_otp(59).as_string(), "4287082")
+}
+
+#[test]
+fn test_try_new_invalid_digits() {
+    let res = TOTP::try_new(SECRET_BYTES_SHA1, MacDigest::SHA1, 30, 6);
+    assert_eq!(res.unwrap_err(), OtpError::InvalidDigits(30))
+}
+
+#[test]
+fn test_try_new_invalid_period() {
+    let res = TOTP::try_new(SECRET_BYTES_SHA1, MacDigest::SHA1, 6, 0);
+    assert_eq!(res.unwrap_err(), OtpError::InvalidPeriod(0))
+}
+
+// Tests to check the comparison between two generators.
+#[test]
+fn test_produces_same_codes() {
+    let totp_bytes = TOTP::new(SECRET_BYTES_SHA1, MacDigest::SHA1, 8, 30);
+    let totp_base32 = TOTP::new_from_base32(SECRET_BASE32_SHA1, MacDigest::SHA1, 8, 30);
+    let times = [59, 1111111109, 1234567890, 2000000000];
+    assert!(totp_bytes.produces_same_codes(&totp_base32, &times));
+    assert_eq!(totp_bytes.first_divergent_time(&totp_base32, &times), None)
+}
+
+#[test]
+fn test_produces_different_codes() {
+    let totp_8_digits = TOTP::new(SECRET_BYTES_SHA1, MacDigest::SHA1, 8, 30);
+    let totp_6_digits = TOTP::new(SECRET_BYTES_SHA1, MacDigest::SHA1, 6, 30);
+    let totp_sha256 = TOTP::new(SECRET_BYTES_SHA1, MacDigest::SHA256, 8, 30);
+    let times = [59, 1111111109];
+    assert!(!totp_8_digits.produces_same_codes(&totp_6_digits, &times));
+    assert_eq!(totp_8_digits.first_divergent_time(&totp_sha256, &times), Some(59))
+}
+
+#[test]
+fn test_acceptable_codes() {
+    let totp = TOTP::new(SECRET_BYTES_SHA1, MacDigest::SHA1, 8, 30);
+    let codes = totp.acceptable_codes(89, 1, 1);
+    assert_eq!(codes.len(), 3);
+    assert!(codes.contains(&totp.get_otp(59).as_string()));
+    assert!(codes.contains("94287082"));
+}
+
+#[test]
+fn test_acceptable_codes_padded() {
+    let totp = TOTP::new(SECRET_BYTES_SHA1, MacDigest::SHA1, 8, 30);
+    let codes = totp.acceptable_codes(1111111109, 0, 0);
+    assert!(codes.contains("07081804"));
+}
+
+// Tests to check the degenerate output detection.
+#[test]
+fn test_no_degenerate_output() {
+    let totp = TOTP::new(SECRET_BYTES_SHA1, MacDigest::SHA1, 8, 30);
+    assert!(!totp.has_degenerate_output(1234567890, 100));
+}
+
+#[test]
+fn test_degenerate_output() {
+    // A single digit only allows 10 different codes, so adjacent repeats
+    // show up quickly
+    let totp = TOTP::new(SECRET_BYTES_SHA1, MacDigest::SHA1, 1, 30);
+    assert!(totp.has_degenerate_output(1234567890, 100));
+}
+
+#[test]
+fn test_degenerate_output_capped_steps() {
+    // The steps are capped, so a huge count neither allocates nor hashes
+    // more than MAX_WINDOW_STEPS codes
+    let totp = TOTP::new(SECRET_BYTES_SHA1, MacDigest::SHA1, 8, 30);
+    assert!(!totp.has_degenerate_output(1234567890, u64::MAX));
+    assert!(!totp.has_degenerate_output(1234567890, 1));
+}
+
+// Tests to check codes longer than 8 digits.
+#[test]
+fn test_9_digits() {
+    let totp = TOTP::try_new(SECRET_BYTES_SHA1, MacDigest::SHA1, 9, 30).unwrap();
+    let otp = totp.get_otp(59);
+    assert_eq!(otp.as_string(), "094287082");
+    assert_eq!(otp.as_u64(), 94287082);
+}
+
+#[test]
+fn test_10_digits_constrained_leading_digit() {
+    let totp = TOTP::try_new(SECRET_BYTES_SHA1, MacDigest::SHA1, 10, 30).unwrap();
+    for step in 0..100 {
+        let otp = totp.get_otp(step * 30).as_string();
+        assert_eq!(otp.len(), 10);
+        assert!(matches!(otp.chars().next(), Some('0'..='2')));
+    }
+}
+
+// Tests to check the secret-free configuration view.
+#[test]
+fn test_config() {
+    let totp = TOTP::new(SECRET_BYTES_SHA256, MacDigest::SHA256, 8, 60);
+    let config = TotpConfig::from(&totp);
+    assert_eq!(config.digits, 8);
+    assert_eq!(config.period, 60);
+    assert_eq!(config.algorithm, MacDigest::SHA256);
+    assert!(!format!("{:?}", config).contains("secret"));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_config_serialize() {
+    let totp = TOTP::new(SECRET_BYTES_SHA1, MacDigest::SHA1, 6, 30);
+    let json = serde_json::to_string(&TotpConfig::from(&totp)).unwrap();
+    assert_eq!(json, r#"{"digits":6,"period":30,"algorithm":"SHA1"}"#);
+}
+
+// Tests to check generation from a duration.
+#[test]
+fn test_get_otp_from_duration() {
+    let totp = TOTP::new(SECRET_BYTES_SHA1, MacDigest::SHA1, 8, 10);
+    for millis in [0, 9_999, 10_000, 1_234_567_890_500] {
+        let duration = Duration::from_millis(millis);
+        assert_eq!(
+            totp.get_otp_from_duration(duration),
+            totp.get_otp(duration.as_secs())
+        );
+    }
+}
+
+// Tests to check the underflow policies with a time before the start time.
+#[test]
+#[should_panic]
+fn test_underflow_error() {
+    let totp = TOTP::new_with_epoch(SECRET_BYTES_SHA1, MacDigest::SHA1, 8, 30, 1000);
+    assert_eq!(totp.get_underflow_policy(), UnderflowPolicy::Error);
+    totp.get_otp(999);
+}
+
+#[test]
+#[should_panic]
+fn test_underflow_error_with_start() {
+    let totp = TOTP::new(SECRET_BYTES_SHA1, MacDigest::SHA1, 8, 30);
+    totp.get_otp_with_custom_time_start(10, 20);
+}
+
+#[test]
+fn test_underflow_saturate() {
+    let totp = TOTP::new_with_epoch(SECRET_BYTES_SHA1, MacDigest::SHA1, 8, 30, 1000)
+        .with_underflow_policy(UnderflowPolicy::Saturate);
+    assert_eq!(totp.get_otp(999), totp.get_otp(1000));
+    assert_eq!(totp.get_otp_with_custom_time_start(10, 20), totp.get_otp(1000));
+}
+
+// A secret longer than the SHA256 and SHA512 block sizes (64 and 128 bytes).
+// The expected codes come from Python's reference `hmac` module.
+#[test]
+fn test_secret_longer_than_block_size() {
+    let secret: Vec<u8> = (0..200).collect();
+    let totp_sha256 = TOTP::new(&secret, MacDigest::SHA256, 8, 30);
+    let totp_sha512 = TOTP::new(&secret, MacDigest::SHA512, 8, 30);
+    assert_eq!(totp_sha256.get_otp(59).as_string(), "15657596");
+    assert_eq!(totp_sha512.get_otp(59).as_string(), "87518001");
+}
+
+// Tests to check the system clock methods.
+#[test]
+fn test_verify_now() {
+    let totp = TOTP::new(SECRET_BYTES_SHA1, MacDigest::SHA1, 8, 30);
+    let otp = totp.get_otp_now();
+    // The period may roll over between the two calls, so retry once
+    assert!(totp.verify_now(&otp.as_string(), 0, 0) || {
+        let otp = totp.get_otp_now();
+        totp.verify_now(&otp.as_string(), 0, 0)
+    });
+}
+
+// Tests to check the prepared generator.
+#[test]
+fn test_prepared() {
+    let totp = TOTP::new(SECRET_BYTES_SHA1, MacDigest::SHA1, 8, 30);
+    let prepared = totp.prepared();
+    assert_eq!(prepared.get_otp(59).as_u32(), 94287082);
+    assert_eq!(prepared.get_otp(1111111109).as_u32(), 7081804);
+    assert_eq!(prepared.get_otp(1234567890).as_u32(), 89005924);
+}
+
+#[test]
+fn test_prepared_with_digest() {
+    for (secret, digest) in [
+        (SECRET_BYTES_SHA256, MacDigest::SHA256),
+        (SECRET_BYTES_SHA512, MacDigest::SHA512),
+    ] {
+        let totp = TOTP::new_with_epoch(secret, digest, 8, 30, 100);
+        let prepared = totp.prepared();
+        for time in [159, 1111111109, 20000000000] {
+            assert_eq!(prepared.get_otp(time), totp.get_otp(time));
+        }
+    }
+}
+
+#[test]
+fn test_from_vec() {
+    let totp = TOTP::from_vec(SECRET_BYTES_SHA1.to_vec(), MacDigest::SHA1, 8, 30);
+    assert_eq!(totp.get_otp(59).as_u32(), 94287082)
+}
+
+// Tests to check codes in a radix other than 10.
+#[test]
+fn test_get_otp_radix_hex() {
+    let totp = TOTP::new(SECRET_BYTES_SHA1, MacDigest::SHA1, 6, 30);
+    for time in [59, 1111111109, 1234567890, 2000000000] {
+        let otp = totp.get_otp_radix(time, 16, 6);
+        let code = otp.as_string();
+        assert_eq!(otp.get_radix(), 16);
+        assert_eq!(code.len(), 6);
+        assert!(code.chars().all(|c| c.is_ascii_hexdigit()));
+    }
+}
+
+#[test]
+fn test_get_otp_radix_decimal() {
+    let totp = TOTP::new(SECRET_BYTES_SHA1, MacDigest::SHA1, 8, 30);
+    assert_eq!(totp.get_otp_radix(59, 10, 8), totp.get_otp(59));
+}
+
+#[cfg(feature = "testing")]
+#[test]
+fn test_demo() {
+    let totp = TOTP::demo();
+    assert_eq!(totp.get_digest(), MacDigest::SHA1);
+    assert_eq!(totp.get_otp(59).as_string(), "287082")
+}
+
+// Tests to check the truncation offset settings.
+#[test]
+fn test_truncation_offset_fixed() {
+    let totp = TOTP::new(SECRET_BYTES_SHA1, MacDigest::SHA1, 6, 30);
+    let fixed = totp
+        .clone()
+        .with_truncation_offset(TruncationOffset::Fixed(0))
+        .unwrap();
+    // The time-step of time 59 is 1, like the HOTP count 1 test
+    assert_eq!(totp.get_otp(59).as_string(), "287082");
+    assert_eq!(fixed.get_otp(59).as_string(), "717529");
+}
+
+#[test]
+fn test_truncation_offset_fixed_digest_len() {
+    let totp = TOTP::new(SECRET_BYTES_SHA512, MacDigest::SHA512, 6, 30);
+    assert!(totp
+        .clone()
+        .with_truncation_offset(TruncationOffset::Fixed(60))
+        .is_ok());
+    assert_eq!(
+        totp.with_truncation_offset(TruncationOffset::Fixed(61))
+            .unwrap_err(),
+        OtpError::InvalidTruncationOffset(61)
+    );
+}
+
+// Tests to check the otpauth URI conversions.
+#[test]
+fn test_to_uri() {
+    let totp = TOTP::new(SECRET_BYTES_SHA1, MacDigest::SHA256, 8, 60);
+    assert_eq!(
+        totp.to_uri("john.doe@email.com", Some("ACME Co")),
+        "otpauth://totp/ACME%20Co:john.doe@email.com?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&issuer=ACME+Co&algorithm=SHA256&digits=8&period=60"
+    );
+}
+
+#[test]
+fn test_to_uri_with_image_param() {
+    let totp = TOTP::default_from_secret(SECRET_BYTES_SHA1);
+    assert_eq!(
+        totp.to_uri_with_params(
+            "john.doe@email.com",
+            None,
+            &[("image", "https://example.com/logo.png?size=64&dark=1")]
+        ),
+        "otpauth://totp/john.doe@email.com?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&algorithm=SHA1&digits=6&period=30&image=https%3A%2F%2Fexample.com%2Flogo.png%3Fsize%3D64%26dark%3D1"
+    );
+}
+
+#[test]
+fn test_to_uri_round_trip() {
+    use xotp::util::{parse_otpauth_uri, ParseResult};
+
+    let totp = TOTP::new(SECRET_BYTES_SHA1, MacDigest::SHA512, 8, 60);
+    let uri = totp.to_uri_with_params("john.doe@email.com", Some("ACME Co"), &[("image", "https://example.com/logo.png")]);
+    match parse_otpauth_uri(&uri) {
+        Ok(ParseResult::TOTP(parsed)) => assert_eq!(parsed.get_otp(59), totp.get_otp(59)),
+        _ => panic!("Failed to parse the generated URI"),
+    }
+}
+
+#[test]
+fn test_from_uri() {
+    let totp = TOTP::new(SECRET_BYTES_SHA1, MacDigest::SHA256, 8, 60);
+    let parsed = TOTP::from_uri(&totp.to_uri("john.doe@email.com", None)).unwrap();
+    assert_eq!(parsed.get_digest(), MacDigest::SHA256);
+    assert_eq!(parsed.get_digits(), 8);
+    assert_eq!(parsed.get_period(), 60);
+    assert_eq!(parsed.get_otp(59), totp.get_otp(59));
+}
+
+#[test]
+fn test_from_uri_hotp() {
+    use xotp::util::ParseError;
+
+    let res = TOTP::from_uri("otpauth://hotp/alice@google.com?secret=JBSWY3DPEHPK3PXP&counter=0");
+    assert!(matches!(res, Err(ParseError::UnexpectedOtpType(x)) if x == "hotp"));
+}
+
+// FromStr accepts either a full otpauth URI or a bare base32 secret
+#[test]
+fn test_from_str_uri() {
+    let totp: TOTP = "otpauth://totp/Example:alice@google.com?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&digits=8&period=60"
+        .parse()
+        .unwrap();
+    assert_eq!(totp.get_digits(), 8);
+    assert_eq!(totp.get_period(), 60);
+    assert_eq!(totp.get_otp(59), TOTP::new(SECRET_BYTES_SHA1, MacDigest::SHA1, 8, 60).get_otp(59));
+}
+
+#[test]
+fn test_from_str_base32() {
+    let totp: TOTP = SECRET_BASE32_SHA1.parse().unwrap();
+    assert_eq!(totp.get_digest(), MacDigest::SHA1);
+    assert_eq!(totp.get_digits(), 6);
+    assert_eq!(totp.get_period(), 30);
+    assert_eq!(totp.get_otp(59).as_string(), "287082");
+}
+
+#[test]
+fn test_from_str_invalid() {
+    use xotp::util::ParseError;
+
+    let res = "not a secret".parse::<TOTP>();
+    assert!(matches!(res, Err(ParseError::SecretParsingError(x)) if x == "not a secret"));
+    assert!(matches!("".parse::<TOTP>(), Err(ParseError::MissingSecret)));
+    assert!(matches!("  ".parse::<TOTP>(), Err(ParseError::MissingSecret)));
+    assert!("otpauth://totp/alice?secret=not-base32".parse::<TOTP>().is_err());
+}
+
+// Tests whether a clone, whose secret is copied into a new zeroizing
+// buffer, still produces the same codes.
+#[cfg(feature = "zeroize")]
+#[test]
+fn test_zeroizing_clone() {
+    let totp = TOTP::new(SECRET_BYTES_SHA1, MacDigest::SHA1, 8, 30);
+    let cloned = totp.clone();
+    drop(totp);
+    assert_eq!(cloned.get_otp(59).as_string(), "94287082");
+    assert_eq!(cloned.get_otp(1111111109).as_string(), "07081804")
+}
+
+// Tests for the side-by-side codes of all digests
+#[test]
+fn test_get_otp_all_digests() {
+    let totp = TOTP::new(SECRET_BYTES_SHA1, MacDigest::SHA1, 8, 30);
+    let results = totp.get_otp_all_digests(59);
+    let digests: Vec<MacDigest> = results.iter().map(|(digest, _)| *digest).collect();
+    assert_eq!(digests, [MacDigest::SHA1, MacDigest::SHA256, MacDigest::SHA512]);
+    assert_eq!(results[0].1.as_string(), "94287082");
+    assert_ne!(results[0].1, results[1].1);
+    assert_ne!(results[0].1, results[2].1);
+    assert_ne!(results[1].1, results[2].1);
+}
+
+#[test]
+fn test_get_otp_all_digests_matches_single_digest() {
+    let totp = TOTP::new(SECRET_BYTES_SHA1, MacDigest::SHA1, 8, 30);
+    for (digest, result) in totp.get_otp_all_digests(1111111109) {
+        let single = TOTP::new(SECRET_BYTES_SHA1, digest, 8, 30);
+        assert_eq!(result, single.get_otp(1111111109));
+    }
+}
+
+// Tests for the non-panicking generation
+#[test]
+fn test_try_get_otp() {
+    let totp = TOTP::new(SECRET_BYTES_SHA1, MacDigest::SHA1, 8, 30);
+    assert_eq!(totp.try_get_otp(59), Ok(totp.get_otp(59)));
+}
+
+#[test]
+fn test_try_get_otp_zero_period() {
+    let totp = TOTP::new(SECRET_BYTES_SHA1, MacDigest::SHA1, 6, 0);
+    assert_eq!(totp.try_get_otp(59), Err(OtpError::InvalidPeriod(0)));
+}
+
+#[test]
+fn test_try_get_otp_time_before_epoch() {
+    let totp = TOTP::new_with_epoch(SECRET_BYTES_SHA1, MacDigest::SHA1, 6, 30, 100);
+    assert_eq!(
+        totp.try_get_otp(50),
+        Err(OtpError::TimeBeforeEpoch {
+            time: 50,
+            epoch: 100
+        })
+    );
+}
+
+#[test]
+fn test_try_get_otp_too_many_digits() {
+    let totp = TOTP::new(SECRET_BYTES_SHA1, MacDigest::SHA1, 25, 30);
+    assert_eq!(totp.try_get_otp(59), Err(OtpError::InvalidDigits(25)));
+}
+
+// Feeds pathological configurations and times, which must either generate
+// a code or return an error, but never panic.
+#[test]
+fn test_try_get_otp_never_panics() {
+    let long_secret = [0xffu8; 200];
+    let secrets: [&[u8]; 4] = [&[], &[0], SECRET_BYTES_SHA1, &long_secret];
+    let digests = [MacDigest::SHA1, MacDigest::SHA256, MacDigest::SHA512];
+    let periods = [0, 1, 30, u64::MAX];
+    let times = [0, 1, 59, u64::MAX];
+    for secret in secrets {
+        for digest in digests {
+            for digits in [0, 1, 6, 10, 19, 20, u32::MAX] {
+                for period in periods {
+                    for epoch in [0, 60, u64::MAX] {
+                        let totp = TOTP::new_with_epoch(secret, digest, digits, period, epoch);
+                        for time in times {
+                            let _ = totp.try_get_otp(time);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Tests for the batch generation at specific times
+#[test]
+fn test_get_otps_at() {
+    let totp = TOTP::new(SECRET_BYTES_SHA1, MacDigest::SHA1, 8, 30);
+    let times = [59, 1111111109, 1111111111, 1234567890, 59];
+    let results = totp.get_otps_at(&times);
+    assert_eq!(results.len(), times.len());
+    for (time, result) in times.iter().zip(&results) {
+        assert_eq!(*result, totp.get_otp(*time));
+    }
+    assert_eq!(results[3].as_string(), "89005924");
+}
+
+#[test]
+fn test_get_otps_at_empty() {
+    let totp = TOTP::default_from_secret(SECRET_BYTES_SHA1);
+    assert!(totp.get_otps_at(&[]).is_empty());
+}
+
+// Tests for the code classification around a time
+#[test]
+fn test_classify_current() {
+    let totp = TOTP::new(SECRET_BYTES_SHA1, MacDigest::SHA1, 8, 30);
+    let code = totp.get_otp(1111111109).as_string();
+    assert_eq!(totp.classify(&code, 1111111109), CodeValidity::Current);
+}
+
+#[test]
+fn test_classify_previous() {
+    let totp = TOTP::new(SECRET_BYTES_SHA1, MacDigest::SHA1, 8, 30);
+    let code = totp.get_otp(1111111109 - 30).as_string();
+    assert_eq!(totp.classify(&code, 1111111109), CodeValidity::Previous);
+}
+
+#[test]
+fn test_classify_next() {
+    let totp = TOTP::new(SECRET_BYTES_SHA1, MacDigest::SHA1, 8, 30);
+    let code = totp.get_otp(1111111109 + 30).as_string();
+    assert_eq!(totp.classify(&code, 1111111109), CodeValidity::Next);
+}
+
+#[test]
+fn test_classify_invalid() {
+    let totp = TOTP::new(SECRET_BYTES_SHA1, MacDigest::SHA1, 8, 30);
+    let code = totp.get_otp(1111111109 - 60).as_string();
+    assert_eq!(totp.classify(&code, 1111111109), CodeValidity::Invalid);
+    assert_eq!(totp.classify("not a code", 1111111109), CodeValidity::Invalid);
+}
+
+// Tests for the constructors from secrecy secrets
+#[cfg(feature = "secrecy")]
+#[test]
+fn test_new_from_secret_base32() {
+    use secrecy::SecretString;
+
+    let secret = SecretString::new(String::from(SECRET_BASE32_SHA1));
+    let totp = TOTP::new_from_secret_base32(&secret, MacDigest::SHA1, 8, 30).unwrap();
+    assert_eq!(totp.get_otp(59).as_string(), "94287082");
+}
+
+#[cfg(feature = "secrecy")]
+#[test]
+fn test_new_from_secret_base32_invalid() {
+    use secrecy::SecretString;
+
+    let secret = SecretString::new(String::from("GEZDGNB1"));
+    let res = TOTP::new_from_secret_base32(&secret, MacDigest::SHA1, 8, 30);
+    assert!(matches!(res, Err(OtpError::InvalidBase32 { position: 7, .. })));
+}
+
+#[cfg(feature = "secrecy")]
+#[test]
+fn test_new_from_secret_vec() {
+    use secrecy::SecretVec;
+
+    let secret = SecretVec::new(SECRET_BYTES_SHA1.to_vec());
+    let totp = TOTP::new_from_secret_vec(&secret, MacDigest::SHA1, 8, 30);
+    assert_eq!(totp.get_otp(1111111109).as_string(), "07081804");
+}
+
+// Tests for the time-step index of a time
+#[test]
+fn test_time_step() {
+    let totp = TOTP::new(SECRET_BYTES_SHA1, MacDigest::SHA1, 8, 30);
+    assert_eq!(totp.time_step(59), 1);
+    assert_eq!(totp.time_step(60), 2);
+    assert_eq!(totp.time_step(1111111109), 0x23523EC);
+}
+
+#[test]
+fn test_get_otp_with_step() {
+    let totp = TOTP::new(SECRET_BYTES_SHA1, MacDigest::SHA1, 8, 30);
+    for time in [0, 59, 1111111109, 1111111111, 1234567890] {
+        let (step, otp) = totp.get_otp_with_step(time);
+        assert_eq!(step, totp.time_step(time));
+        assert_eq!(otp, totp.get_otp(time));
+    }
+}
+
+// Tests for the strict secret length validation
+#[test]
+fn test_try_new_strict_short_secret() {
+    let res = TOTP::try_new_strict(&[0u8; 16], MacDigest::SHA512, 6, 30);
+    assert_eq!(
+        res.unwrap_err(),
+        OtpError::SecretTooShort {
+            length: 16,
+            minimum: 64
+        }
+    );
+}
+
+#[test]
+fn test_try_new_strict_long_enough_secret() {
+    let totp = TOTP::try_new_strict(SECRET_BYTES_SHA512, MacDigest::SHA512, 8, 30).unwrap();
+    assert_eq!(totp.get_otp(59).as_string(), "90693936");
+    assert!(TOTP::try_new_strict(SECRET_BYTES_SHA1, MacDigest::SHA1, 6, 30).is_ok());
+}
+
+#[test]
+fn test_try_new_short_secret_not_strict() {
+    assert!(TOTP::try_new(&[0u8; 16], MacDigest::SHA512, 6, 30).is_ok());
+}
+
+#[test]
+fn test_try_new_strict_invalid_digits() {
+    let res = TOTP::try_new_strict(SECRET_BYTES_SHA1, MacDigest::SHA1, 0, 30);
+    assert_eq!(res.unwrap_err(), OtpError::InvalidDigits(0));
+}
+
+#[test]
+fn test_time_step_bounds() {
+    let totp = TOTP::new(SECRET_BYTES_SHA1, MacDigest::SHA1, 8, 30);
+    assert_eq!(totp.time_step_bounds(59), (30, 60));
+    assert_eq!(totp.time_step_bounds(60), (60, 90));
+    for time in [0, 1, 29, 30, 59, 1111111109, 1234567890] {
+        let (start, end) = totp.time_step_bounds(time);
+        assert!(start <= time && time < end);
+        assert_eq!(start % 30, 0);
+        assert_eq!(end - start, 30);
+    }
+}
+
+#[test]
+fn test_time_step_bounds_with_epoch() {
+    let totp = TOTP::new_with_epoch(SECRET_BYTES_SHA1, MacDigest::SHA1, 6, 30, 10);
+    assert_eq!(totp.time_step_bounds(45), (40, 70));
+    for time in 10..200 {
+        let (start, end) = totp.time_step_bounds(time);
+        assert!(start <= time && time < end);
+        assert_eq!((start - 10) % 30, 0);
+    }
+}
+
+#[test]
+fn test_verify_full_width_digits() {
+    let totp = TOTP::new(SECRET_BYTES_SHA1, MacDigest::SHA1, 8, 30);
+    assert!(totp.verify("９４２８７０８２", 59, 0, 0));
+    assert_eq!(totp.classify("٩٤٢٨٧٠٨٢", 59), CodeValidity::Current);
+}
+
+// Tests for the flat parameter list conversions
+#[test]
+fn test_to_params() {
+    let totp = TOTP::new(SECRET_BYTES_SHA1, MacDigest::SHA256, 8, 60);
+    let params = totp.to_params();
+    let expected = [
+        ("secret_base32", SECRET_BASE32_SHA1),
+        ("algorithm", "SHA256"),
+        ("digits", "8"),
+        ("period", "60"),
+    ];
+    assert_eq!(params.len(), expected.len());
+    for ((key, value), (expected_key, expected_value)) in params.iter().zip(expected) {
+        assert_eq!(key, expected_key);
+        assert_eq!(value, expected_value);
+    }
+}
+
+#[test]
+fn test_params_round_trip() {
+    let totp = TOTP::new_with_epoch(SECRET_BYTES_SHA1, MacDigest::SHA512, 8, 60, 100);
+    let params = totp.to_params();
+    let parsed = TOTP::from_params(&params).unwrap();
+    assert_eq!(parsed.get_digest(), MacDigest::SHA512);
+    assert_eq!(parsed.get_digits(), 8);
+    assert_eq!(parsed.get_period(), 60);
+    assert_eq!(parsed.get_epoch(), 100);
+    assert_eq!(parsed.get_otp(1111111109), totp.get_otp(1111111109));
+}
+
+#[test]
+fn test_from_params_missing() {
+    let mut params = TOTP::default_from_secret(SECRET_BYTES_SHA1).to_params();
+    params.retain(|(key, _)| key != "period");
+    assert_eq!(
+        TOTP::from_params(&params).unwrap_err(),
+        OtpError::MissingParam(String::from("period"))
+    );
+}
+
+#[test]
+fn test_from_params_invalid() {
+    let params = vec![
+        (String::from("secret_base32"), String::from(SECRET_BASE32_SHA1)),
+        (String::from("algorithm"), String::from("MD5")),
+        (String::from("digits"), String::from("6")),
+        (String::from("period"), String::from("30")),
+    ];
+    assert_eq!(
+        TOTP::from_params(&params).unwrap_err(),
+        OtpError::InvalidParam(String::from("algorithm"))
+    );
+}
+
+#[test]
+fn test_from_params_invalid_digits() {
+    let params_with_digits = |digits: &str| {
+        vec![
+            (String::from("secret_base32"), String::from(SECRET_BASE32_SHA1)),
+            (String::from("algorithm"), String::from("SHA1")),
+            (String::from("digits"), String::from(digits)),
+            (String::from("period"), String::from("30")),
+        ]
+    };
+    assert_eq!(
+        TOTP::from_params(&params_with_digits("six")).unwrap_err(),
+        OtpError::InvalidParam(String::from("digits"))
+    );
+    assert_eq!(
+        TOTP::from_params(&params_with_digits("4294967296")).unwrap_err(),
+        OtpError::InvalidParam(String::from("digits"))
+    );
+    assert_eq!(
+        TOTP::from_params(&params_with_digits("11")).unwrap_err(),
+        OtpError::InvalidDigits(11)
+    );
+}
+
+#[test]
+fn test_from_params_empty_secret() {
+    let params = vec![
+        (String::from("secret_base32"), String::new()),
+        (String::from("algorithm"), String::from("SHA1")),
+        (String::from("digits"), String::from("6")),
+        (String::from("period"), String::from("30")),
+    ];
+    assert_eq!(
+        TOTP::from_params(&params).unwrap_err(),
+        OtpError::MissingParam(String::from("secret_base32"))
+    );
+}
+
+// Tests for the generation from calendar dates
+#[cfg(feature = "calendar")]
+#[test]
+fn test_get_otp_from_utc() {
+    let totp = TOTP::new(SECRET_BYTES_SHA1, MacDigest::SHA1, 8, 30);
+    // 2009-02-13 23:31:30 UTC is 1234567890
+    assert_eq!(
+        totp.get_otp_from_utc(2009, 2, 13, 23, 31, 30).unwrap().as_string(),
+        "89005924"
+    );
+    // 2005-03-18 01:58:29 UTC is 1111111109
+    assert_eq!(
+        totp.get_otp_from_utc(2005, 3, 18, 1, 58, 29).unwrap(),
+        totp.get_otp(1111111109)
+    );
+    assert_eq!(totp.get_otp_from_utc(1970, 1, 1, 0, 0, 59).unwrap(), totp.get_otp(59));
+}
+
+#[cfg(feature = "calendar")]
+#[test]
+fn test_get_otp_from_utc_leap_day() {
+    let totp = TOTP::new(SECRET_BYTES_SHA1, MacDigest::SHA1, 8, 30);
+    // 2000-02-29 12:00:00 UTC is 951825600, and 2100-03-01 00:00:00 UTC is 4107542400
+    assert_eq!(totp.get_otp_from_utc(2000, 2, 29, 12, 0, 0).unwrap(), totp.get_otp(951825600));
+    assert_eq!(totp.get_otp_from_utc(2100, 3, 1, 0, 0, 0).unwrap(), totp.get_otp(4107542400));
+}
+
+#[cfg(feature = "calendar")]
+#[test]
+fn test_get_otp_from_utc_invalid_date() {
+    let totp = TOTP::new(SECRET_BYTES_SHA1, MacDigest::SHA1, 8, 30);
+    for (year, month, day, hour, minute, second) in [
+        (2009, 2, 29, 0, 0, 0),
+        (2100, 2, 29, 0, 0, 0),
+        (2009, 13, 1, 0, 0, 0),
+        (2009, 0, 1, 0, 0, 0),
+        (2009, 4, 31, 0, 0, 0),
+        (2009, 1, 0, 0, 0, 0),
+        (2009, 1, 1, 24, 0, 0),
+        (2009, 1, 1, 0, 60, 0),
+        (2009, 1, 1, 0, 0, 60),
+        (1969, 12, 31, 23, 59, 59),
+    ] {
+        assert_eq!(
+            totp.get_otp_from_utc(year, month, day, hour, minute, second),
+            Err(OtpError::InvalidDate)
+        );
+    }
+}
+
+// Tests for the configuration warnings
+#[test]
+fn test_lint_default() {
+    let totp = TOTP::default_from_secret(SECRET_BYTES_SHA1);
+    assert!(totp.lint().is_empty());
+}
+
+#[test]
+fn test_lint_short_period() {
+    let totp = TOTP::new(SECRET_BYTES_SHA1, MacDigest::SHA1, 6, 5);
+    let warnings = totp.lint();
+    assert_eq!(warnings, [ConfigWarning::ShortPeriod(5)]);
+    assert_eq!(warnings[0].code(), "short_period");
+    assert!(warnings[0].to_string().contains("5 seconds"));
+}
+
+#[test]
+fn test_lint_ten_digits() {
+    let totp = TOTP::new(SECRET_BYTES_SHA1, MacDigest::SHA1, 10, 30);
+    let warnings = totp.lint();
+    assert_eq!(warnings, [ConfigWarning::ManyDigits(10)]);
+    assert_eq!(warnings[0].code(), "many_digits");
+    assert_eq!(warnings[0].to_string(), warnings[0].message());
+}
+
+#[test]
+fn test_lint_multiple() {
+    let totp = TOTP::new(SECRET_BYTES_SHA256, MacDigest::SHA256, 4, 10);
+    assert_eq!(
+        totp.lint(),
+        [
+            ConfigWarning::ShortPeriod(10),
+            ConfigWarning::FewDigits(4),
+            ConfigWarning::UncommonDigest(MacDigest::SHA256)
+        ]
+    );
+}
+
+// Tests for the HMAC message of a time
+#[test]
+fn test_moving_factor_bytes() {
+    let totp = TOTP::new(SECRET_BYTES_SHA1, MacDigest::SHA1, 8, 30);
+    assert_eq!(totp.moving_factor_bytes(59), [0, 0, 0, 0, 0, 0, 0, 1]);
+    // The RFC6238 test vector table lists T as 00000000023523EC for 1111111109
+    assert_eq!(totp.moving_factor_bytes(1111111109), [0, 0, 0, 0, 0x02, 0x35, 0x23, 0xEC]);
+}
+
+#[test]
+fn test_truncation_offset_method_dynamic() {
+    let totp = TOTP::new(SECRET_BYTES_SHA1, MacDigest::SHA1, 8, 30);
+    for time in (0..100_000).step_by(30) {
+        let offset = totp.truncation_offset(time);
+        assert!(offset <= 15);
+        // Truncating at the reported offset gives back the same code
+        let fixed = TOTP::new(SECRET_BYTES_SHA1, MacDigest::SHA1, 8, 30)
+            .with_truncation_offset(TruncationOffset::Fixed(offset))
+            .unwrap();
+        assert_eq!(fixed.get_otp(time), totp.get_otp(time));
+    }
+}
+
+#[test]
+fn test_truncation_offset_method_fixed() {
+    let totp = TOTP::new(SECRET_BYTES_SHA1, MacDigest::SHA1, 8, 30)
+        .with_truncation_offset(TruncationOffset::Fixed(12))
+        .unwrap();
+    assert_eq!(totp.truncation_offset(59), 12);
+    assert_eq!(totp.truncation_offset(1111111109), 12);
+}
+
+// Tests for the digest allow-list
+#[test]
+fn test_new_with_allowed_digests_rejects_sha1() {
+    let allowed = [MacDigest::SHA256, MacDigest::SHA512];
+    let res = TOTP::new_with_allowed_digests(SECRET_BYTES_SHA1, MacDigest::SHA1, 6, 30, &allowed);
+    assert_eq!(res.unwrap_err(), OtpError::DisallowedAlgorithm(MacDigest::SHA1));
+}
+
+#[test]
+fn test_new_with_allowed_digests_accepts() {
+    let allowed = [MacDigest::SHA256, MacDigest::SHA512];
+    let totp =
+        TOTP::new_with_allowed_digests(SECRET_BYTES_SHA256, MacDigest::SHA256, 8, 30, &allowed)
+            .unwrap();
+    assert_eq!(totp.get_otp(59).as_string(), "46119246");
+}
+
+#[test]
+fn test_new_with_allowed_digests_invalid_digits() {
+    let res = TOTP::new_with_allowed_digests(
+        SECRET_BYTES_SHA1,
+        MacDigest::SHA1,
+        0,
+        30,
+        &[MacDigest::SHA1],
+    );
+    assert_eq!(res.unwrap_err(), OtpError::InvalidDigits(0));
+}
+
+// Tests for the number of periods in a time range
+#[test]
+fn test_periods_between() {
+    let totp = TOTP::default_from_secret(SECRET_BYTES_SHA1);
+    assert_eq!(totp.periods_between(1111111110, 1111111410), Ok(10));
+    assert_eq!(totp.periods_between(1111111109, 1111111409), Ok(10));
+    assert_eq!(totp.periods_between(59, 59), Ok(0));
+}
+
+#[test]
+fn test_periods_between_partial() {
+    let totp = TOTP::default_from_secret(SECRET_BYTES_SHA1);
+    assert_eq!(totp.periods_between(0, 315), Ok(10));
+    assert_eq!(totp.periods_between(0, 29), Ok(0));
+}
+
+#[test]
+fn test_periods_between_reversed() {
+    let totp = TOTP::default_from_secret(SECRET_BYTES_SHA1);
+    assert_eq!(
+        totp.periods_between(100, 50),
+        Err(OtpError::InvalidTimeRange { start: 100, end: 50 })
+    );
+}
+
+// Tests that the cached HMAC state (with the mac-cache feature) doesn't
+// change the generated codes or the identity of a generator.
+#[test]
+fn test_repeated_get_otp_hash_unchanged() {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
+    let hash = |totp: &TOTP| {
+        let mut hasher = DefaultHasher::new();
+        totp.hash(&mut hasher);
+        hasher.finish()
+    };
+    let totp = TOTP::new(SECRET_BYTES_SHA1, MacDigest::SHA1, 8, 30);
+    let before = hash(&totp);
+    assert_eq!(totp.get_otp(59).as_string(), "94287082");
+    assert_eq!(totp.get_otp(1111111109).as_string(), "07081804");
+    assert_eq!(hash(&totp), before);
+
+    let cloned = totp.clone();
+    assert_eq!(hash(&cloned), before);
+    assert_eq!(cloned.get_otp(1234567890).as_string(), "89005924");
+}
+
+// Tests for the skew offsets and their histogram
+#[test]
+fn test_verify_with_skew() {
+    let totp = TOTP::new(SECRET_BYTES_SHA1, MacDigest::SHA1, 8, 30);
+    let code = totp.get_otp(1111111109).as_string();
+    assert_eq!(totp.verify_with_skew(&code, 1111111109, 2, 2), Some(0));
+    assert_eq!(totp.verify_with_skew(&code, 1111111109 + 60, 2, 2), Some(-2));
+    assert_eq!(totp.verify_with_skew(&code, 1111111109 - 30, 2, 2), Some(1));
+    assert_eq!(totp.verify_with_skew(&code, 1111111109 + 90, 2, 2), None);
+}
+
+#[test]
+fn test_skew_histogram_summary() {
+    let mut histogram = SkewHistogram::new();
+    for offset in [0, 0, 0, -1, -1, 1, -3] {
+        histogram.record(offset);
+    }
+    let summary = histogram.summary();
+    assert_eq!(histogram.total(), 7);
+    assert_eq!(summary.mean, -4.0 / 7.0);
+    assert_eq!(summary.max_abs, 3);
+    assert_eq!(
+        summary.counts_per_offset.into_iter().collect::<Vec<_>>(),
+        [(-3, 1), (-1, 2), (0, 3), (1, 1)]
+    );
+}
+
+#[test]
+fn test_skew_histogram_from_verifications() {
+    let totp = TOTP::new(SECRET_BYTES_SHA1, MacDigest::SHA1, 8, 30);
+    let mut histogram = SkewHistogram::new();
+    for client_time in [1111111109, 1111111109 - 30, 1111111109 - 30] {
+        let code = totp.get_otp(client_time).as_string();
+        if let Some(offset) = totp.verify_with_skew(&code, 1111111109, 1, 1) {
+            histogram.record(offset);
+        }
+    }
+    let summary = histogram.summary();
+    assert_eq!(summary.mean, -2.0 / 3.0);
+    assert_eq!(summary.max_abs, 1);
+}
+
+#[test]
+fn test_skew_histogram_empty() {
+    let summary = SkewHistogram::new().summary();
+    assert_eq!(summary.mean, 0.0);
+    assert_eq!(summary.max_abs, 0);
+    assert!(summary.counts_per_offset.is_empty());
+}
+
+// Tests for the extended codes of more than 8 digits
+#[test]
+fn test_get_otp_extended_all_digits_vary() {
+    let totp = TOTP::new(SECRET_BYTES_SHA1, MacDigest::SHA1, 10, 30);
+    let mut extended_leading = std::collections::HashSet::new();
+    let mut standard_leading = std::collections::HashSet::new();
+    for step in 0..200 {
+        let time = 1111111109 + step * 30;
+        let extended = totp.get_otp_extended(time).as_string();
+        assert_eq!(extended.len(), 10);
+        extended_leading.insert(extended.chars().next().unwrap());
+        standard_leading.insert(totp.get_otp(time).as_string().chars().next().unwrap());
+    }
+    // A standard 10-digit code is below 2^31, so it starts with 0, 1 or 2
+    assert!(standard_leading.len() <= 3);
+    assert_eq!(extended_leading.len(), 10);
+}
+
+#[test]
+fn test_get_otp_extended_low_digits() {
+    let totp = TOTP::new(SECRET_BYTES_SHA1, MacDigest::SHA1, 10, 30);
+    let extended = totp.get_otp_extended(1111111109).as_string();
+    assert!(extended.ends_with("07081804"));
+    assert_ne!(extended, totp.get_otp(1111111109).as_string());
+}
+
+#[test]
+fn test_get_otp_extended_short_codes() {
+    let totp = TOTP::new(SECRET_BYTES_SHA1, MacDigest::SHA1, 8, 30);
+    assert_eq!(totp.get_otp_extended(59), totp.get_otp(59));
+}
+
+// Tests for the constructor from explicitly encoded secrets
+#[test]
+fn test_from_secret() {
+    use xotp::util::{Base32Secret, RawSecret, Utf8Secret};
+
+    let from_utf8 = TOTP::from_secret(Utf8Secret(SECRET_UTF8_SHA1), MacDigest::SHA1, 8, 30).unwrap();
+    let from_base32 =
+        TOTP::from_secret(Base32Secret(SECRET_BASE32_SHA1), MacDigest::SHA1, 8, 30).unwrap();
+    let from_raw = TOTP::from_secret(RawSecret(SECRET_BYTES_SHA1), MacDigest::SHA1, 8, 30).unwrap();
+    for totp in [from_utf8, from_base32, from_raw] {
+        assert_eq!(totp.get_otp(59).as_string(), "94287082");
+    }
+}
+
+#[test]
+fn test_from_secret_invalid_base32() {
+    use xotp::util::Base32Secret;
+
+    let res = TOTP::from_secret(Base32Secret("SecretKey"), MacDigest::SHA1, 6, 30);
+    assert_eq!(res.unwrap_err(), OtpError::InvalidBase32Length(9));
+}
+
+// Tests for the error on times before a future epoch, as from a device with
+// an uninitialized clock
+#[test]
+fn test_try_get_otp_now_before_future_epoch() {
+    let totp = TOTP::new_with_epoch(SECRET_BYTES_SHA1, MacDigest::SHA1, 6, 30, u64::MAX);
+    assert!(matches!(
+        totp.try_get_otp_now(),
+        Err(OtpError::TimeBeforeEpoch { epoch: u64::MAX, .. })
+    ));
+    assert_eq!(
+        totp.try_get_otp(0),
+        Err(OtpError::TimeBeforeEpoch {
+            time: 0,
+            epoch: u64::MAX
+        })
+    );
+}
+
+#[test]
+fn test_try_get_otp_now_saturate_before_epoch() {
+    let totp = TOTP::new_with_epoch(SECRET_BYTES_SHA1, MacDigest::SHA1, 6, 30, u64::MAX)
+        .with_underflow_policy(UnderflowPolicy::Saturate);
+    assert_eq!(totp.try_get_otp_now(), Ok(totp.get_otp(u64::MAX)));
+}
+
+#[test]
+fn test_try_get_otp_now() {
+    let totp = TOTP::default_from_secret(SECRET_BYTES_SHA1);
+    assert!(totp.try_get_otp_now().is_ok());
+}
+
+// Huge windows are capped instead of allocating every step, and steps
+// past the end of the u64 range are skipped
+#[test]
+fn test_verify_huge_window() {
+    let totp = TOTP::new(SECRET_BYTES_SHA1, MacDigest::SHA1, 8, 30);
+    assert!(totp.verify("94287082", 59, u64::MAX, 0));
+    assert!(totp.verify("94287082", 59, u64::MAX, u64::MAX));
+    assert_eq!(totp.verify_with_skew("94287082", 59, 0, u64::MAX), Some(0));
+    assert!(!totp.verify("00000000", 59, u64::MAX, u64::MAX));
+    // The code 1001 steps ahead is outside of the capped window
+    let far = totp.get_otp(59 + 1001 * 30).as_string();
+    assert_eq!(totp.verify_with_skew(&far, 59, 1001, 0), None);
+    let near = totp.get_otp(59 + 1000 * 30).as_string();
+    assert_eq!(totp.verify_with_skew(&near, 59, 1001, 0), Some(1000));
+}
+
+// Offsets are computed from the distance to the current step, so steps
+// above i64::MAX don't overflow
+#[test]
+fn test_verify_with_skew_large_steps() {
+    let totp = TOTP::new(SECRET_BYTES_SHA1, MacDigest::SHA1, 8, 1);
+    let code = totp.get_otp(u64::MAX - 1).as_string();
+    assert_eq!(totp.verify_with_skew(&code, u64::MAX, 5, 5), Some(-1));
+    assert_eq!(totp.verify_with_skew(&code, u64::MAX - 3, 5, 5), Some(2));
+}
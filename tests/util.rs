@@ -1,6 +1,6 @@
 use xotp::util::ParseError;
 use xotp::util::ParseResult;
-use xotp::util::{parse_otpauth_uri, MacDigest};
+use xotp::util::{base32_decode_verbose, parse_otpauth_uri, uri_config_eq, MacDigest, OtpError};
 
 // Examples
 // otpauth://totp/Example:alice@google.com?secret=JBSWY3DPEHPK3PXP&issuer=Example
@@ -30,7 +30,7 @@ fn test_otpauth_parse_missing_otp_type() {
     assert!(res.is_err());
     assert!(matches!(
         res.unwrap_err(),
-        ParseError::MissingOtpType { .. }
+        ParseError::MissingOtpType
     ));
 }
 
@@ -50,7 +50,7 @@ fn test_otpauth_parse_invalid_otp_type() {
 fn test_otpauth_parse_missing_secret() {
     let res = parse_otpauth_uri("otpauth://totp/Example:alice@google.com?issuer=Example");
     assert!(res.is_err());
-    assert!(matches!(res.unwrap_err(), ParseError::MissingSecret { .. }));
+    assert!(matches!(res.unwrap_err(), ParseError::MissingSecret));
 }
 
 #[test]
@@ -73,7 +73,18 @@ fn test_otpauth_parse_invalid_digits_2() {
     assert!(res.is_err());
     assert!(matches!(
         res.unwrap_err(),
-        ParseError::WrongDigitNumber { .. }
+        ParseError::MalformedUri { param, value } if param == "digits" && value == "abc"
+    ));
+}
+
+#[test]
+fn test_otpauth_parse_invalid_digits_3() {
+    let res = parse_otpauth_uri(
+        "otpauth://totp/Example:alice@google.com?secret=JBSWY3DPEHPK3PXP&issuer=Example&digits=100",
+    );
+    assert!(matches!(
+        res.unwrap_err(),
+        ParseError::WrongDigitNumber(digits) if digits == "100"
     ));
 }
 
@@ -136,7 +147,10 @@ fn test_otpauth_parse_totp_invalid_period_2() {
         "otpauth://totp/ACME%20Co:john.doe@email.com?secret=HXDMVJECJJWSRB3HWIZR4IFUGFTMXBOZ&issuer=ACME%20Co&algorithm=SHA256&digits=8&period=abc",
     );
     assert!(res.is_err());
-    assert!(matches!(res.unwrap_err(), ParseError::InvalidPeriod { .. }));
+    assert!(matches!(
+        res.unwrap_err(),
+        ParseError::MalformedUri { param, value } if param == "period" && value == "abc"
+    ));
 }
 
 #[test]
@@ -147,7 +161,7 @@ fn test_otpauth_parse_hotp_missing_counter() {
     assert!(res.is_err());
     assert!(matches!(
         res.unwrap_err(),
-        ParseError::MissingCounter { .. }
+        ParseError::MissingCounter
     ));
 }
 
@@ -185,3 +199,220 @@ fn test_otpauth_parse_hotp_with_digits() {
         panic!();
     }
 }
+
+#[test]
+fn test_digest_output_len() {
+    assert_eq!(MacDigest::SHA1.output_len(), 20);
+    assert_eq!(MacDigest::SHA256.output_len(), 32);
+    assert_eq!(MacDigest::SHA512.output_len(), 64);
+}
+
+#[test]
+fn test_digest_block_size() {
+    assert_eq!(MacDigest::SHA1.block_size(), 64);
+    assert_eq!(MacDigest::SHA256.block_size(), 64);
+    assert_eq!(MacDigest::SHA512.block_size(), 128);
+}
+
+#[test]
+fn test_base32_decode_verbose() {
+    let res = base32_decode_verbose("GEZDGNBVGY3TQOJQ");
+    assert_eq!(res.unwrap(), b"1234567890");
+}
+
+#[test]
+fn test_base32_decode_verbose_lowercase_and_padding() {
+    let res = base32_decode_verbose("gezdgnbvgy3tqojqgeza====");
+    assert_eq!(res.unwrap(), b"123456789012");
+}
+
+#[test]
+fn test_base32_decode_verbose_invalid_character() {
+    let res = base32_decode_verbose("JBSWY3DPEH1K3PXP");
+    assert_eq!(
+        res.unwrap_err(),
+        OtpError::InvalidBase32 {
+            position: 10,
+            character: '1'
+        }
+    );
+}
+
+#[test]
+fn test_base32_decode_verbose_invalid_unicode() {
+    let res = base32_decode_verbose("JBSWÜ3DP");
+    assert_eq!(
+        res.unwrap_err(),
+        OtpError::InvalidBase32 {
+            position: 4,
+            character: 'Ü'
+        }
+    );
+}
+
+#[test]
+fn test_uri_config_eq_different_label() {
+    let res = uri_config_eq(
+        "otpauth://totp/Example:alice@google.com?secret=JBSWY3DPEHPK3PXP&issuer=Example",
+        "otpauth://totp/alice?secret=JBSWY3DPEHPK3PXP&algorithm=SHA1&digits=6&period=30",
+    );
+    assert!(res.unwrap());
+}
+
+#[test]
+fn test_uri_config_eq_different_secret() {
+    let res = uri_config_eq(
+        "otpauth://totp/Example:alice@google.com?secret=JBSWY3DPEHPK3PXP&issuer=Example",
+        "otpauth://totp/Example:alice@google.com?secret=HXDMVJECJJWSRB3HWIZR4IFUGFTMXBOZ&issuer=Example",
+    );
+    assert!(!res.unwrap());
+}
+
+#[test]
+fn test_uri_config_eq_different_period() {
+    let res = uri_config_eq(
+        "otpauth://totp/Example:alice@google.com?secret=JBSWY3DPEHPK3PXP&period=30",
+        "otpauth://totp/Example:alice@google.com?secret=JBSWY3DPEHPK3PXP&period=60",
+    );
+    assert!(!res.unwrap());
+}
+
+#[test]
+fn test_uri_config_eq_hotp_different_counter() {
+    let res = uri_config_eq(
+        "otpauth://hotp/Example:alice@google.com?secret=JBSWY3DPEHPK3PXP&counter=1",
+        "otpauth://hotp/Example:bob@google.com?secret=JBSWY3DPEHPK3PXP&counter=5",
+    );
+    assert!(res.unwrap());
+}
+
+#[test]
+fn test_uri_config_eq_different_type() {
+    let res = uri_config_eq(
+        "otpauth://totp/Example:alice@google.com?secret=JBSWY3DPEHPK3PXP",
+        "otpauth://hotp/Example:alice@google.com?secret=JBSWY3DPEHPK3PXP&counter=1",
+    );
+    assert!(!res.unwrap());
+}
+
+#[test]
+fn test_uri_config_eq_invalid_uri() {
+    let res = uri_config_eq(
+        "otpauth://totp/Example:alice@google.com?secret=JBSWY3DPEHPK3PXP",
+        "otpauth://totp/Example:alice@google.com",
+    );
+    assert_eq!(res.unwrap_err(), OtpError::InvalidUri(ParseError::MissingSecret));
+}
+
+// Tests for the strict RFC4648 trailing bit and length checks
+#[test]
+fn test_base32_decode_verbose_eight_characters() {
+    let res = base32_decode_verbose("JBSWY3DP");
+    assert_eq!(res.unwrap(), b"Hello");
+}
+
+#[test]
+fn test_base32_decode_verbose_zero_trailing_bits() {
+    let res = base32_decode_verbose("GE");
+    assert_eq!(res.unwrap(), b"1");
+}
+
+#[test]
+fn test_base32_decode_verbose_nonzero_trailing_bits() {
+    let res = base32_decode_verbose("GF");
+    assert_eq!(
+        res.unwrap_err(),
+        OtpError::NonZeroBase32TrailingBits {
+            position: 1,
+            character: 'F'
+        }
+    );
+}
+
+#[test]
+fn test_base32_decode_verbose_invalid_length() {
+    let res = base32_decode_verbose("GEZDGNBVG");
+    assert_eq!(res.unwrap_err(), OtpError::InvalidBase32Length(9));
+}
+
+// Tests for the backup code generation
+#[cfg(feature = "rand")]
+#[test]
+fn test_generate_backup_codes() {
+    use std::collections::HashSet;
+    use xotp::util::generate_backup_codes;
+
+    let codes = generate_backup_codes(10, 8).unwrap();
+    assert_eq!(codes.len(), 10);
+    assert!(codes
+        .iter()
+        .all(|code| code.len() == 8 && code.chars().all(|c| c.is_ascii_digit())));
+    let unique: HashSet<&String> = codes.iter().collect();
+    assert_eq!(unique.len(), 10);
+}
+
+#[cfg(feature = "rand")]
+#[test]
+fn test_generate_backup_codes_all_short_codes() {
+    use xotp::util::generate_backup_codes;
+
+    // Every 1-digit code is generated once, including the zero-padded one
+    let mut codes = generate_backup_codes(10, 1).unwrap();
+    codes.sort();
+    assert_eq!(codes, ["0", "1", "2", "3", "4", "5", "6", "7", "8", "9"]);
+}
+
+#[cfg(feature = "rand")]
+#[test]
+fn test_generate_backup_codes_too_many() {
+    use xotp::util::{generate_backup_codes, OtpError};
+
+    assert_eq!(
+        generate_backup_codes(101, 2).unwrap_err(),
+        OtpError::TooManyBackupCodes { count: 101, possible: 100 }
+    );
+    assert_eq!(generate_backup_codes(100, 2).unwrap().len(), 100);
+}
+
+#[cfg(feature = "rand")]
+#[test]
+fn test_generate_backup_codes_invalid_length() {
+    use xotp::util::{generate_backup_codes, OtpError};
+
+    assert_eq!(generate_backup_codes(1, 0).unwrap_err(), OtpError::InvalidDigits(0));
+    assert_eq!(generate_backup_codes(1, 11).unwrap_err(), OtpError::InvalidDigits(11));
+}
+
+// Tests for the explicitly encoded secret inputs
+#[test]
+fn test_secret_input_to_bytes() {
+    use xotp::util::{Base32Secret, RawSecret, SecretInput, Utf8Secret};
+
+    let expected = b"12345678901234567890".to_vec();
+    let utf8 = SecretInput::from(Utf8Secret("12345678901234567890"));
+    let base32 = SecretInput::from(Base32Secret("GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ"));
+    let raw = SecretInput::from(RawSecret(b"12345678901234567890"));
+    assert_eq!(utf8.to_bytes().unwrap(), expected);
+    assert_eq!(base32.to_bytes().unwrap(), expected);
+    assert_eq!(raw.to_bytes().unwrap(), expected);
+}
+
+#[test]
+fn test_secret_input_base32_as_utf8() {
+    use xotp::util::{Base32Secret, SecretInput, Utf8Secret};
+
+    // The same string gives different secrets depending on its encoding
+    let secret = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+    let as_utf8 = SecretInput::from(Utf8Secret(secret)).to_bytes().unwrap();
+    let as_base32 = SecretInput::from(Base32Secret(secret)).to_bytes().unwrap();
+    assert_eq!(as_utf8, secret.as_bytes());
+    assert_eq!(as_base32, b"12345678901234567890");
+}
+
+#[test]
+fn test_secret_input_invalid_base32() {
+    use xotp::util::{Base32Secret, SecretInput};
+
+    let res = SecretInput::from(Base32Secret("GEZDGNB1")).to_bytes();
+    assert!(matches!(res, Err(OtpError::InvalidBase32 { position: 7, .. })));
+}
@@ -0,0 +1,56 @@
+#![cfg(feature = "wasm")]
+
+use xotp::wasm::WasmTotp;
+
+static SECRET_BASE32: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+
+// Tests for the JavaScript bindings, run natively
+#[test]
+fn test_from_base32() {
+    let totp = WasmTotp::from_base32(SECRET_BASE32, "SHA1", 8, 30).unwrap();
+    assert_eq!(totp.get_otp(59.0), Some(String::from("94287082")));
+}
+
+#[test]
+fn test_from_base32_invalid() {
+    assert!(WasmTotp::from_base32("GEZDGNB1", "SHA1", 8, 30).is_none());
+    assert!(WasmTotp::from_base32(SECRET_BASE32, "MD5", 8, 30).is_none());
+    assert!(WasmTotp::from_base32(SECRET_BASE32, "SHA1", 0, 30).is_none());
+    assert!(WasmTotp::from_base32(SECRET_BASE32, "SHA1", 8, 0).is_none());
+}
+
+#[test]
+fn test_verify() {
+    let totp = WasmTotp::from_base32(SECRET_BASE32, "SHA1", 8, 30).unwrap();
+    assert!(totp.verify("94287082", 59.0, 0, 0));
+    assert!(totp.verify("94287082", 89.0, 0, 1));
+    assert!(!totp.verify("94287082", 89.0, 0, 0));
+}
+
+#[test]
+fn test_to_uri() {
+    let totp = WasmTotp::from_base32(SECRET_BASE32, "SHA256", 8, 60).unwrap();
+    assert_eq!(
+        totp.to_uri("john.doe@email.com", Some(String::from("ACME Co"))),
+        "otpauth://totp/ACME%20Co:john.doe@email.com?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&issuer=ACME+Co&algorithm=SHA256&digits=8&period=60"
+    );
+    assert!(totp.to_uri("john.doe@email.com", None).starts_with("otpauth://totp/john.doe@email.com?"));
+}
+
+// JavaScript numbers are passed as f64, with fractional seconds truncated
+#[test]
+fn test_number_times() {
+    let totp = WasmTotp::from_base32(SECRET_BASE32, "SHA1", 8, 30).unwrap();
+    assert_eq!(totp.get_otp(59.999), Some(String::from("94287082")));
+    assert_eq!(totp.get_otp(1111111109.5), Some(String::from("07081804")));
+    assert!(totp.verify("94287082", 59.25, 0, 0));
+}
+
+#[test]
+fn test_invalid_number_times() {
+    let totp = WasmTotp::from_base32(SECRET_BASE32, "SHA1", 8, 30).unwrap();
+    for time in [f64::NAN, f64::INFINITY, -1.0, 1e20] {
+        assert_eq!(totp.get_otp(time), None);
+        assert!(!totp.verify("94287082", time, 1, 1));
+    }
+}